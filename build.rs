@@ -1,9 +1,17 @@
+#[cfg(feature = "c-wrapper")]
 extern crate gcc;
 
+#[cfg(feature = "c-wrapper")]
 use std::env;
 fn main() {
-    if env::var("TARGET").unwrap().contains("linux") {
-        gcc::compile_library("libgetauxval-wrapper.a", &["c/getauxval-wrapper.c"]);
+    // set when building the initial_stack_start example with its own `_start`
+    println!("cargo:rustc-check-cfg=cfg(auxv_custom_start)");
+
+    #[cfg(feature = "c-wrapper")]
+    {
+        if env::var("TARGET").unwrap().contains("linux") {
+            gcc::compile_library("libgetauxval-wrapper.a", &["c/getauxval-wrapper.c"]);
+        }
     }
 
 }
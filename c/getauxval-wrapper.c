@@ -9,37 +9,31 @@ unsigned long getauxval(unsigned long type) __attribute__((weak));
 
 /*
  * getauxval() may or may not be available. In addition, it may not find
- * the key requested.
+ * the key requested, and how it says so depends on the libc version, so
+ * this just reports what happened and leaves the interpretation to Rust.
  * - If getauxval() is not available, this returns -1.
- * - If getauxval() is available but the requested key is not found or another
- *   error occurs, this returns 0.
- * - If getauxval() is available but a different error occurs, this returns -2.
- * - If getauxval() is available and the requested key is found, this returns
- *   1 and also writes to the result pointer param.
+ * - Otherwise, this returns 0, and writes getauxval()'s return value to the
+ *   result pointer param and the errno it set (or 0 if it didn't set one) to
+ *   the error pointer param.
  */
-int32_t getauxval_wrapper(unsigned long key, unsigned long *result);
+int32_t getauxval_wrapper(unsigned long key, unsigned long *result, int32_t *error);
 
 #include <errno.h>
 #include <stddef.h>
 
-int32_t getauxval_wrapper(unsigned long key, unsigned long *result) {
+int32_t getauxval_wrapper(unsigned long key, unsigned long *result, int32_t *error) {
     if (getauxval == NULL) {
         return -1;
     }
 
-    unsigned long auxval = getauxval(key);
-    if (errno == ENOENT) {
-        // as of glibc 2.19, errno is ENOENT if the key is not found.
-        errno = 0;
-        return 0;
-    } else if (errno != 0) {
-        // as of glibc 2.23 the only error is enoent, but more errors
-        // may be added
-        errno = 0;
-        return -2;
-    }
+    // getauxval() only sets errno on failure, so clear out anything left over
+    // from earlier calls, and put it back afterwards
+    int saved_errno = errno;
+    errno = 0;
+    *result = getauxval(key);
+    *error = errno;
+    errno = saved_errno;
 
-    *result = auxval;
-    return 1;
+    return 0;
 }
 #endif
@@ -16,5 +16,14 @@ fn main() {
         for pair in auxv::stack::iterate_stack_auxv() {
             println!("{}\t{}", pair.key, pair.value);
         };
+
+        // with the init-array feature, the auxv location recorded before main is still good
+        #[cfg(feature = "init-array")]
+        {
+            println!("Auxv captured at startup");
+            for pair in auxv::stack::iterate_startup_auxv().unwrap() {
+                println!("{}\t{}", pair.key, pair.value);
+            };
+        }
     };
 }
@@ -3,7 +3,7 @@ extern crate auxv;
 fn main() {
     #[cfg(not(target_os="windows"))]
     unsafe {
-        match auxv::stack::iterate_stack_auxv().filter(|p| p.key == auxv::AT_HWCAP).next() {
+        match auxv::stack::iterate_stack_auxv().find(|p| p.key == auxv::AT_HWCAP) {
             Some(p) => println!("Got HWCAP 0x{:016X}", p.value),
             None => println!("No HWCAP")
         }
@@ -0,0 +1,91 @@
+//! A binary with its own `_start` that reads argc, argv, envp and auxv from the initial stack,
+//! without libc having set anything up. It only uses raw syscalls for output.
+//!
+//! It has to be linked without the C runtime's `_start`, so build it with:
+//!
+//! ```text
+//! cargo rustc --example initial_stack_start -- --cfg auxv_custom_start -C link-arg=-nostartfiles
+//! ./target/debug/examples/initial_stack_start some args
+//! ```
+//!
+//! Built normally, it just prints that.
+
+#![cfg_attr(all(auxv_custom_start, target_os = "linux", target_arch = "x86_64"), no_main)]
+
+extern crate auxv;
+
+#[cfg(all(auxv_custom_start, target_os = "linux", target_arch = "x86_64"))]
+mod custom_start {
+    use std::arch::{asm, global_asm};
+    use std::fmt::{self, Write};
+
+    use auxv::stack::InitialStack;
+
+    // The kernel enters `_start` with the stack pointer at argc. Pass it along as the first
+    // argument, and align the stack the way the ABI expects at a call.
+    global_asm!(
+        ".globl _start",
+        "_start:",
+        "mov rdi, rsp",
+        "and rsp, -16",
+        "call {main}",
+        "ud2",
+        main = sym start_main,
+    );
+
+    unsafe extern "C" fn start_main(sp: *const usize) -> ! {
+        let stack = InitialStack::from_stack_pointer(sp);
+        let mut out = Stdout;
+
+        let _ = writeln!(out, "argc: {}", stack.argc());
+        for arg in stack.args() {
+            let _ = writeln!(out, "arg: {}", arg.to_string_lossy());
+        }
+        let _ = writeln!(out, "env vars: {}", stack.env().count());
+        for pair in stack.auxv() {
+            let _ = writeln!(out, "{}\t{}", pair.key, pair.value);
+        }
+
+        exit(0)
+    }
+
+    /// Writes straight to fd 1 with the `write` syscall.
+    struct Stdout;
+
+    impl Write for Stdout {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let mut bytes = s.as_bytes();
+            while !bytes.is_empty() {
+                let written: isize;
+                unsafe {
+                    asm!("syscall",
+                         inlateout("rax") 1isize => written,
+                         in("rdi") 1,
+                         in("rsi") bytes.as_ptr(),
+                         in("rdx") bytes.len(),
+                         lateout("rcx") _,
+                         lateout("r11") _,
+                         options(nostack));
+                }
+                if written <= 0 {
+                    return Err(fmt::Error);
+                }
+                bytes = &bytes[written as usize..];
+            }
+            Ok(())
+        }
+    }
+
+    fn exit(code: i32) -> ! {
+        unsafe {
+            // exit_group
+            asm!("syscall", in("rax") 231, in("rdi") code, options(noreturn, nostack));
+        }
+    }
+}
+
+#[cfg(not(all(auxv_custom_start, target_os = "linux", target_arch = "x86_64")))]
+fn main() {
+    println!("Build with `cargo rustc --example initial_stack_start -- --cfg auxv_custom_start \
+              -C link-arg=-nostartfiles` on x86_64 Linux to use the custom `_start`");
+}
@@ -0,0 +1,14 @@
+extern crate auxv;
+
+/// Show the program headers of this executable, as found via auxv
+fn main() {
+    match auxv::phdr::ProgramHeaders::current() {
+        Ok(headers) => {
+            println!("type\t\tflags\tvaddr\t\t\tmemsz");
+            for p in headers {
+                println!("0x{:08X}\t{}\t0x{:016X}\t0x{:X}", p.p_type, p.p_flags, p.p_vaddr, p.p_memsz);
+            }
+        }
+        Err(e) => println!("Could not find program headers {:?}", e)
+    }
+}
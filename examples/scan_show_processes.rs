@@ -0,0 +1,26 @@
+extern crate auxv;
+
+use auxv::procfs::ProcfsAuxvError;
+use auxv::scan::ProcessScanner;
+
+fn main() {
+    match ProcessScanner::new().scan() {
+        Ok(processes) => {
+            for p in processes.iter().filter(|p| !p.kernel_thread) {
+                let exe = p.exe.as_ref().map(|e| e.display().to_string()).unwrap_or_default();
+                match p.auxv {
+                    Ok(_) => {
+                        let bits = if p.is_32_bit() { "32-bit" } else { "" };
+                        let secure = if p.is_secure() == Some(true) { "secure" } else { "" };
+                        println!("{}\t{}\t{}\t{}", p.pid, bits, secure, exe)
+                    }
+                    Err(ProcfsAuxvError::PermissionDenied) => {
+                        println!("{}\t(permission denied, try as root)\t\t{}", p.pid, exe)
+                    }
+                    Err(ref e) => println!("{}\t{:?}\t\t{}", p.pid, e, exe)
+                }
+            }
+        }
+        Err(e) => println!("Could not list processes {:?}", e)
+    }
+}
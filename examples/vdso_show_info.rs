@@ -0,0 +1,24 @@
+extern crate auxv;
+
+/// Show what the vDSO says about itself: kernel version, build id, and exported symbols
+fn main() {
+    match auxv::vdso::current_vdso() {
+        Ok(vdso) => {
+            match vdso.kernel_version() {
+                Some(v) => println!("Kernel version {}.{}.{}", v.major, v.minor, v.patch),
+                None => println!("No kernel version note")
+            }
+            match vdso.build_id() {
+                Some(id) => {
+                    let hex: Vec<String> = id.iter().map(|b| format!("{:02x}", b)).collect();
+                    println!("Build id {}", hex.concat());
+                }
+                None => println!("No build id")
+            }
+            for sym in vdso.symbols() {
+                println!("0x{:016X}\t{}@{}", sym.address, sym.name, sym.version.unwrap_or(""));
+            }
+        }
+        Err(e) => println!("Could not find vDSO {:?}", e)
+    }
+}
@@ -0,0 +1,125 @@
+//! Read auxv entries from a buffer that already holds the raw aux vector.
+//!
+//! This is for when the aux vector has been captured somewhere else: a copy of
+//! `/proc/<pid>/auxv`, the auxv stream in a minidump, etc. The buffer must hold key/value pairs
+//! laid out the way the kernel writes them, terminated by an `AT_NULL` entry.
+//!
+//! Like the procfs iterator, the items are `Result`s: a buffer that ends before `AT_NULL` or in
+//! the middle of an entry produces one `InvalidFormat` error and then the iterator stops.
+
+extern crate byteorder;
+
+use std::marker::PhantomData;
+
+use self::byteorder::ByteOrder;
+
+use super::{AuxvPair, AuxvType, ElfClass};
+
+/// Errors from parsing an auxv buffer.
+#[derive(Debug, PartialEq)]
+pub enum BytesAuxvError {
+    /// the auxv data is invalid
+    InvalidFormat
+}
+
+/// Iterate over the auxv pairs in `bytes`, using the width of `AuxvType`.
+pub fn iterate_bytes_auxv<B: ByteOrder>(bytes: &[u8]) -> BytesAuxvIter<'_, B> {
+    iterate_bytes_auxv_class::<B>(bytes, ElfClass::native())
+}
+
+/// Iterate over the auxv pairs in `bytes`, which were produced by a process of the given class.
+///
+/// If `class` is wider than `AuxvType` (64-bit data on a 32-bit system), values are truncated.
+pub fn iterate_bytes_auxv_class<B: ByteOrder>(bytes: &[u8], class: ElfClass)
+                                              -> BytesAuxvIter<'_, B> {
+    BytesAuxvIter {
+        bytes,
+        class,
+        keep_going: true,
+        phantom_byteorder: PhantomData
+    }
+}
+
+/// An iterator across auxv pairs in a byte buffer.
+pub struct BytesAuxvIter<'a, B: ByteOrder> {
+    bytes: &'a [u8],
+    class: ElfClass,
+    keep_going: bool,
+    phantom_byteorder: PhantomData<B>
+}
+
+impl<'a, B: ByteOrder> Iterator for BytesAuxvIter<'a, B> {
+    type Item = Result<AuxvPair, BytesAuxvError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.keep_going {
+            return None
+        }
+
+        let word_size = self.class.word_size();
+        if self.bytes.len() < 2 * word_size {
+            // should not hit the end before AT_NULL
+            self.keep_going = false;
+            return Some(Err(BytesAuxvError::InvalidFormat));
+        }
+
+        let (key, value) = match self.class {
+            ElfClass::Elf32 => (B::read_u32(self.bytes) as AuxvType,
+                                B::read_u32(&self.bytes[4..]) as AuxvType),
+            ElfClass::Elf64 => (B::read_u64(self.bytes) as AuxvType,
+                                B::read_u64(&self.bytes[8..]) as AuxvType),
+        };
+        self.bytes = &self.bytes[2 * word_size..];
+
+        // AT_NULL (0) signals the end of auxv
+        if key == 0 {
+            self.keep_going = false;
+            return None;
+        }
+
+        Some(Ok(AuxvPair { key, value }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{iterate_bytes_auxv_class, BytesAuxvError};
+    use super::byteorder::LittleEndian;
+    use super::super::{AuxvPair, ElfClass};
+    use super::super::test_support::read_fixture;
+
+    #[test]
+    fn test_iterate_bytes_virtualbox_linux_x86_as_elf32() {
+        let bytes = read_fixture("macos-virtualbox-linux-x86-4850HQ.auxv");
+        let pairs: Vec<AuxvPair> = iterate_bytes_auxv_class::<LittleEndian>(&bytes, ElfClass::Elf32)
+            .map(|r| r.unwrap())
+            .collect();
+
+        assert_eq!(19, pairs.len());
+        // x86 AT_SYSINFO
+        assert_eq!(AuxvPair { key: 32, value: 3078061308 }, pairs[0]);
+        // AT_PLATFORM
+        assert_eq!(AuxvPair { key: 15, value: 3219671675 }, pairs[18]);
+    }
+
+    #[test]
+    fn test_iterate_bytes_real_linux_x64_as_elf64() {
+        let bytes = read_fixture("linux-x64-i7-6850k.auxv");
+        let pairs: Vec<AuxvPair> = iterate_bytes_auxv_class::<LittleEndian>(&bytes, ElfClass::Elf64)
+            .map(|r| r.unwrap())
+            .collect();
+
+        assert_eq!(18, pairs.len());
+        // AT_HWCAP
+        assert_eq!(AuxvPair { key: 16, value: 3219913727 }, pairs[1]);
+    }
+
+    #[test]
+    fn test_iterate_bytes_truncated_entry_invalidformat() {
+        let bytes = read_fixture("linux-x64-i7-6850k-mangled-truncated-entry.auxv");
+        let mut iter = iterate_bytes_auxv_class::<LittleEndian>(&bytes, ElfClass::Elf64).skip(17);
+
+        // entry for key 15 is missing its value
+        assert_eq!(BytesAuxvError::InvalidFormat, iter.next().unwrap().unwrap_err());
+        assert_eq!(None, iter.next());
+    }
+}
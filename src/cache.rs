@@ -0,0 +1,213 @@
+//! A process-wide copy of the current process's aux vector.
+//!
+//! `search_procfs_auxv` re-reads `/proc/self/auxv` and builds a `HashMap` every time, which is
+//! fine once at startup but not in a hot path. The aux vector of a running process never changes,
+//! though, so `cached_auxv` reads it once, the first time it's needed, and keeps it in an
+//! `AuxvTable` from then on. Looking up a key in the table doesn't allocate, lock, or do I/O, and
+//! the table can be shared freely between threads.
+//!
+//! The table is filled from the best source available, in this order:
+//!
+//! - the auxv found by the `init-array` constructor, if that feature is on and it ran
+//! - `/proc/self/auxv`
+//! - the initial stack, found via `/proc/self/stat` so a modified environment doesn't matter
+//!
+//! If none of those work, the table is empty and `AuxvTable::source` is `None`. `getauxval` can't
+//! be used to fill it since it can only look up keys one at a time, not list them.
+
+use std::sync::OnceLock;
+
+use super::{AuxvPair, AuxvType};
+
+/// Keys below this are stored directly in an array. Every key Linux currently defines is.
+pub const TABLE_KEYS: usize = 64;
+
+/// Where an `AuxvTable` came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuxvSource {
+    /// the auxv found by the `init-array` constructor
+    Startup,
+    /// `/proc/self/auxv`
+    Procfs,
+    /// the initial stack
+    Stack,
+}
+
+/// An immutable aux vector with constant-time lookups.
+#[derive(Debug, Clone)]
+pub struct AuxvTable {
+    /// values of keys below `TABLE_KEYS`, indexed by key
+    values: [AuxvType; TABLE_KEYS],
+    /// bit `n` is set if key `n` is in `values`
+    present: u64,
+    /// any keys too large for `values`, in the order they appeared
+    overflow: Vec<AuxvPair>,
+    source: Option<AuxvSource>,
+}
+
+impl AuxvTable {
+    /// Build a table from pairs read from `source`. If a key appears more than once, the first one
+    /// wins, as with `AuxvSnapshot`.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn from_pairs<I>(pairs: I, source: Option<AuxvSource>) -> AuxvTable
+        where I: IntoIterator<Item = AuxvPair> {
+        let mut table = AuxvTable {
+            values: [0; TABLE_KEYS],
+            present: 0,
+            overflow: Vec::new(),
+            source,
+        };
+
+        for pair in pairs {
+            if table.get(pair.key).is_some() {
+                continue;
+            }
+            if (pair.key as u64) < TABLE_KEYS as u64 {
+                table.values[pair.key as usize] = pair.value;
+                table.present |= 1 << pair.key;
+            } else {
+                table.overflow.push(pair);
+            }
+        }
+
+        table
+    }
+
+    /// Look up the value for `key`.
+    ///
+    /// This is an array index for keys below `TABLE_KEYS`, and a scan of the (normally empty) list
+    /// of larger keys otherwise.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn get(&self, key: AuxvType) -> Option<AuxvType> {
+        if (key as u64) < TABLE_KEYS as u64 {
+            if self.present & (1 << key) != 0 {
+                Some(self.values[key as usize])
+            } else {
+                None
+            }
+        } else {
+            self.overflow.iter()
+                .find(|p| p.key == key)
+                .map(|p| p.value)
+        }
+    }
+
+    /// Where the entries came from, or `None` if no source could be read and the table is empty.
+    pub fn source(&self) -> Option<AuxvSource> {
+        self.source
+    }
+
+    /// The entries in the table, ordered by key.
+    pub fn pairs(&self) -> Vec<AuxvPair> {
+        (0..TABLE_KEYS)
+            .filter(|&k| self.present & (1 << k) != 0)
+            .map(|k| AuxvPair { key: k as AuxvType, value: self.values[k] })
+            .chain(self.overflow.iter().cloned())
+            .collect()
+    }
+
+    /// The number of entries in the table.
+    pub fn len(&self) -> usize {
+        self.present.count_ones() as usize + self.overflow.len()
+    }
+
+    /// True if the table has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+static CACHED_AUXV: OnceLock<AuxvTable> = OnceLock::new();
+
+/// The current process's aux vector, read the first time this is called.
+///
+/// Threads that call this while another is reading the aux vector wait for it to finish; after
+/// that, this is just an atomic load.
+pub fn cached_auxv() -> &'static AuxvTable {
+    CACHED_AUXV.get_or_init(read_current_auxv)
+}
+
+/// Look up `key` in the current process's aux vector via `cached_auxv`.
+pub fn cached_auxv_value(key: AuxvType) -> Option<AuxvType> {
+    cached_auxv().get(key)
+}
+
+/// Read the aux vector from the first source that works.
+fn read_current_auxv() -> AuxvTable {
+    #[cfg(all(feature = "init-array", not(target_os="windows")))]
+    {
+        if let Some(iter) = super::stack::iterate_startup_auxv() {
+            return AuxvTable::from_pairs(iter, Some(AuxvSource::Startup));
+        }
+    }
+
+    let procfs = super::procfs::iterate_procfs_auxv(std::process::id())
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>());
+    if let Ok(pairs) = procfs {
+        return AuxvTable::from_pairs(pairs, Some(AuxvSource::Procfs));
+    }
+
+    #[cfg(not(target_os="windows"))]
+    {
+        let stack = super::stack::iterate_stack_auxv_verified()
+            .and_then(|iter| iter.collect::<Result<Vec<_>, _>>());
+        if let Ok(pairs) = stack {
+            return AuxvTable::from_pairs(pairs, Some(AuxvSource::Stack));
+        }
+    }
+
+    AuxvTable::from_pairs(Vec::new(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::AuxvPair;
+    use super::{AuxvSource, AuxvTable, TABLE_KEYS};
+
+    fn pair(key: u64, value: u64) -> AuxvPair {
+        AuxvPair { key: key as super::super::AuxvType, value: value as super::super::AuxvType }
+    }
+
+    #[test]
+    fn test_table_get() {
+        let table = AuxvTable::from_pairs(vec![pair(6, 4096), pair(23, 0), pair(63, 7)],
+                                          Some(AuxvSource::Procfs));
+
+        assert_eq!(Some(4096), table.get(6));
+        assert_eq!(Some(0), table.get(23));
+        assert_eq!(Some(7), table.get(63));
+        assert_eq!(None, table.get(0));
+        assert_eq!(None, table.get(16));
+        assert_eq!(3, table.len());
+        assert_eq!(Some(AuxvSource::Procfs), table.source());
+    }
+
+    #[test]
+    fn test_table_large_keys() {
+        let table = AuxvTable::from_pairs(vec![pair(TABLE_KEYS as u64, 1), pair(0x1000, 2)], None);
+
+        assert_eq!(Some(1), table.get(TABLE_KEYS as super::super::AuxvType));
+        assert_eq!(Some(2), table.get(0x1000));
+        assert_eq!(None, table.get(0x1001));
+        assert_eq!(vec![pair(TABLE_KEYS as u64, 1), pair(0x1000, 2)], table.pairs());
+    }
+
+    #[test]
+    fn test_table_first_duplicate_wins() {
+        let table = AuxvTable::from_pairs(vec![pair(6, 1), pair(0x1000, 2), pair(6, 3),
+                                               pair(0x1000, 4)], None);
+
+        assert_eq!(Some(1), table.get(6));
+        assert_eq!(Some(2), table.get(0x1000));
+        assert_eq!(2, table.len());
+    }
+
+    #[test]
+    fn test_table_empty() {
+        let table = AuxvTable::from_pairs(Vec::new(), None);
+
+        assert!(table.is_empty());
+        assert_eq!(None, table.source());
+        assert_eq!(None, table.get(6));
+    }
+}
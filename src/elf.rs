@@ -0,0 +1,489 @@
+//! Just enough ELF to make sense of the images that auxv entries point at.
+//!
+//! Several auxv values are addresses of ELF data: `AT_SYSINFO_EHDR` is the vDSO's ELF header,
+//! `AT_PHDR` is the main executable's program headers, and so on. This module reads those
+//! structures out of byte slices, with bounds checks everywhere, so that a malformed image
+//! produces an error rather than a crash.
+//!
+//! Only images with the same class and byte order as the current process are supported, since
+//! the addresses in auxv always refer to the current process's own images.
+
+extern crate byteorder;
+
+use self::byteorder::{ByteOrder, NativeEndian};
+
+use super::ElfClass;
+
+/// Loadable segment
+pub const PT_LOAD: u32 = 1;
+/// Dynamic linking information
+pub const PT_DYNAMIC: u32 = 2;
+/// Path of the program interpreter
+pub const PT_INTERP: u32 = 3;
+/// Auxiliary information (notes)
+pub const PT_NOTE: u32 = 4;
+/// The program header table itself
+pub const PT_PHDR: u32 = 6;
+/// Thread-local storage template
+pub const PT_TLS: u32 = 7;
+/// Stack executability
+pub const PT_GNU_STACK: u32 = 0x6474_e551;
+/// Read-only after relocation
+pub const PT_GNU_RELRO: u32 = 0x6474_e552;
+/// GNU property notes
+pub const PT_GNU_PROPERTY: u32 = 0x6474_e553;
+
+/// GNU build ID note type (owner `GNU`)
+pub const NT_GNU_BUILD_ID: u32 = 3;
+/// GNU program properties note type (owner `GNU`)
+pub const NT_GNU_PROPERTY_TYPE_0: u32 = 5;
+
+/// End of the dynamic section
+pub const DT_NULL: usize = 0;
+/// Address of the SysV symbol hash table
+pub const DT_HASH: usize = 4;
+/// Address of the string table
+pub const DT_STRTAB: usize = 5;
+/// Address of the symbol table
+pub const DT_SYMTAB: usize = 6;
+/// Filled in at runtime by the dynamic linker with the address of its `r_debug`
+pub const DT_DEBUG: usize = 21;
+/// Address of the GNU symbol hash table
+pub const DT_GNU_HASH: usize = 0x6fff_fef5;
+/// Address of the symbol version table
+pub const DT_VERSYM: usize = 0x6fff_fff0;
+/// Address of the version definition table
+pub const DT_VERDEF: usize = 0x6fff_fffc;
+
+/// A 32-bit program header (`Elf32_Phdr`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Elf32Phdr {
+    pub p_type: u32,
+    pub p_offset: u32,
+    pub p_vaddr: u32,
+    pub p_paddr: u32,
+    pub p_filesz: u32,
+    pub p_memsz: u32,
+    pub p_flags: u32,
+    pub p_align: u32,
+}
+
+/// A 64-bit program header (`Elf64_Phdr`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Elf64Phdr {
+    pub p_type: u32,
+    pub p_flags: u32,
+    pub p_offset: u64,
+    pub p_vaddr: u64,
+    pub p_paddr: u64,
+    pub p_filesz: u64,
+    pub p_memsz: u64,
+    pub p_align: u64,
+}
+
+/// The program header type used by the current process.
+#[cfg(target_pointer_width="32")]
+pub type ElfPhdr = Elf32Phdr;
+/// The program header type used by the current process.
+#[cfg(target_pointer_width="64")]
+pub type ElfPhdr = Elf64Phdr;
+
+// e_ident
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const EI_CLASS: usize = 4;
+const EI_DATA: usize = 5;
+#[cfg(target_pointer_width="32")]
+const NATIVE_CLASS: u8 = 1;
+#[cfg(target_pointer_width="64")]
+const NATIVE_CLASS: u8 = 2;
+#[cfg(target_endian="little")]
+const NATIVE_DATA: u8 = 1;
+#[cfg(target_endian="big")]
+const NATIVE_DATA: u8 = 2;
+
+/// The size of the ELF header for the current process's class.
+#[cfg(target_pointer_width="32")]
+pub const EHDR_SIZE: usize = 52;
+/// The size of the ELF header for the current process's class.
+#[cfg(target_pointer_width="64")]
+pub const EHDR_SIZE: usize = 64;
+
+const WORD: usize = std::mem::size_of::<usize>();
+
+/// Errors from parsing an ELF image.
+#[derive(Debug, PartialEq)]
+pub enum ElfError {
+    /// the data is not a well-formed ELF image
+    InvalidFormat,
+    /// the image's class or byte order doesn't match the current process
+    UnsupportedClass
+}
+
+/// An ELF image held in memory, e.g. the vDSO or a file read into a buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct ElfImage<'a> {
+    bytes: &'a [u8],
+    e_type: u16,
+    e_entry: usize,
+    e_phoff: usize,
+    e_phentsize: usize,
+    e_phnum: usize,
+    e_shoff: usize,
+    e_shentsize: usize,
+    e_shnum: usize,
+}
+
+impl<'a> ElfImage<'a> {
+    /// Parse the ELF header at the start of `bytes`.
+    ///
+    /// `bytes` only needs to hold as much of the image as you intend to look at; lookups past the
+    /// end are reported as errors or missing data, not panics.
+    pub fn parse(bytes: &'a [u8]) -> Result<ElfImage<'a>, ElfError> {
+        if bytes.len() < EHDR_SIZE || &bytes[..4] != ELF_MAGIC {
+            return Err(ElfError::InvalidFormat);
+        }
+        if bytes[EI_CLASS] != NATIVE_CLASS || bytes[EI_DATA] != NATIVE_DATA {
+            return Err(ElfError::UnsupportedClass);
+        }
+
+        // e_ident is 16 bytes, then e_type and e_machine (u16), then e_version (u32), and
+        // everything after that is laid out in terms of words until e_flags
+        let e_type = NativeEndian::read_u16(&bytes[16..]);
+        let e_entry = read_word(bytes, 24).ok_or(ElfError::InvalidFormat)?;
+        let e_phoff = read_word(bytes, 24 + WORD).ok_or(ElfError::InvalidFormat)?;
+        let e_shoff = read_word(bytes, 24 + 2 * WORD).ok_or(ElfError::InvalidFormat)?;
+        // skip e_flags (u32) and e_ehsize (u16)
+        let halves = 24 + 3 * WORD + 6;
+
+        let image = ElfImage {
+            bytes,
+            e_type,
+            e_entry,
+            e_phoff,
+            e_phentsize: NativeEndian::read_u16(&bytes[halves..]) as usize,
+            e_phnum: NativeEndian::read_u16(&bytes[halves + 2..]) as usize,
+            e_shoff,
+            e_shentsize: NativeEndian::read_u16(&bytes[halves + 4..]) as usize,
+            e_shnum: NativeEndian::read_u16(&bytes[halves + 6..]) as usize,
+        };
+
+        if image.e_phnum > 0 && image.e_phentsize != std::mem::size_of::<ElfPhdr>() {
+            return Err(ElfError::InvalidFormat);
+        }
+        // the header tables must at least fit in the address space
+        let table_end = |offset: usize, size: usize, count: usize| {
+            size.checked_mul(count).and_then(|len| offset.checked_add(len))
+        };
+        if table_end(image.e_phoff, image.e_phentsize, image.e_phnum).is_none()
+            || table_end(image.e_shoff, image.e_shentsize, image.e_shnum).is_none() {
+            return Err(ElfError::InvalidFormat);
+        }
+
+        Ok(image)
+    }
+
+    /// The bytes the image was parsed from.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The object file type (`e_type`), e.g. 2 for `ET_EXEC` or 3 for `ET_DYN`.
+    pub fn elf_type(&self) -> u16 {
+        self.e_type
+    }
+
+    /// The entry point address (`e_entry`).
+    pub fn entry(&self) -> usize {
+        self.e_entry
+    }
+
+    /// Iterate over the program headers. Headers that lie outside the available bytes are not
+    /// returned.
+    pub fn program_headers(&self) -> ElfPhdrIter<'a> {
+        ElfPhdrIter {
+            bytes: self.bytes,
+            offset: self.e_phoff,
+            remaining: self.e_phnum,
+        }
+    }
+
+    /// The offset of the program header table (`e_phoff`).
+    pub fn program_header_offset(&self) -> usize {
+        self.e_phoff
+    }
+
+    /// The size of each program header (`e_phentsize`).
+    pub fn program_header_size(&self) -> usize {
+        self.e_phentsize
+    }
+
+    /// The number of program headers (`e_phnum`).
+    pub fn program_header_count(&self) -> usize {
+        self.e_phnum
+    }
+
+    /// The offset just past the end of the program header table.
+    ///
+    /// When reading an image out of memory, this is how many bytes are needed before
+    /// `program_headers` (and therefore `extent`) can see everything.
+    pub fn program_headers_end(&self) -> usize {
+        self.e_phoff.saturating_add(self.e_phentsize * self.e_phnum)
+    }
+
+    /// The length of the image according to its headers: enough to cover every segment's file
+    /// contents and the section header table.
+    ///
+    /// This is how much memory needs to be read to get the whole image when all you have is the
+    /// address of its header.
+    pub fn extent(&self) -> usize {
+        let sections_end = self.e_shoff.saturating_add(self.e_shentsize * self.e_shnum);
+        self.program_headers()
+            .map(|p| p.p_offset.saturating_add(p.p_filesz) as usize)
+            .fold(std::cmp::max(sections_end, self.program_headers_end()), std::cmp::max)
+    }
+
+    /// Translate a virtual address (as used in the dynamic section, symbol values, etc) to an
+    /// offset in the image, using the `PT_LOAD` segment that contains it.
+    pub fn vaddr_to_offset(&self, vaddr: usize) -> Option<usize> {
+        self.program_headers()
+            .filter(|p| p.p_type == PT_LOAD)
+            .find(|p| vaddr >= p.p_vaddr as usize && vaddr - (p.p_vaddr as usize) < p.p_filesz as usize)
+            .and_then(|p| (vaddr - p.p_vaddr as usize).checked_add(p.p_offset as usize))
+    }
+
+    /// The file contents of a segment, if they are within the available bytes.
+    pub fn segment_bytes(&self, phdr: &ElfPhdr) -> Option<&'a [u8]> {
+        let start = phdr.p_offset as usize;
+        let end = start.checked_add(phdr.p_filesz as usize)?;
+        self.bytes.get(start..end)
+    }
+
+    /// Iterate over the notes in all of the `PT_NOTE` segments.
+    pub fn notes(&self) -> ElfNoteIter<'a> {
+        ElfNoteIter {
+            image: *self,
+            phdrs: self.program_headers(),
+            current: None,
+        }
+    }
+
+    /// Iterate over the entries in the `PT_DYNAMIC` segment as `(tag, value)`, stopping at
+    /// `DT_NULL`.
+    pub fn dynamic_entries(&self) -> DynamicIter<'a> {
+        let bytes = self.program_headers()
+            .find(|p| p.p_type == PT_DYNAMIC)
+            .and_then(|p| self.segment_bytes(&p))
+            .unwrap_or(&[]);
+        DynamicIter { bytes }
+    }
+}
+
+/// An iterator over the program headers of an `ElfImage`.
+pub struct ElfPhdrIter<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for ElfPhdrIter<'a> {
+    type Item = ElfPhdr;
+    fn next(&mut self) -> Option<ElfPhdr> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let phdr = read_phdr(self.bytes, self.offset);
+        match self.offset.checked_add(std::mem::size_of::<ElfPhdr>()) {
+            Some(next) if phdr.is_some() => self.offset = next,
+            _ => self.remaining = 0,
+        }
+        phdr
+    }
+}
+
+/// An ELF note.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElfNote<'a> {
+    /// The owner of the note, e.g. `GNU` or `Linux`, without the trailing NUL
+    pub name: &'a [u8],
+    /// The note type, whose meaning depends on the owner
+    pub note_type: u32,
+    /// The note contents
+    pub desc: &'a [u8],
+}
+
+/// Iterate over the notes in a note segment or section.
+///
+/// `align` is the alignment of the segment: 4 for most notes, 8 for some (like GNU properties)
+/// on 64-bit systems.
+pub fn iterate_notes(bytes: &[u8], align: usize) -> NoteIter<'_> {
+    NoteIter {
+        bytes,
+        align: if align == 8 { 8 } else { 4 },
+    }
+}
+
+/// An iterator over the notes in a single note segment.
+pub struct NoteIter<'a> {
+    bytes: &'a [u8],
+    align: usize,
+}
+
+impl<'a> Iterator for NoteIter<'a> {
+    type Item = ElfNote<'a>;
+    fn next(&mut self) -> Option<ElfNote<'a>> {
+        let note = self.parse_next();
+        if note.is_none() {
+            // malformed or finished; either way, there's nothing more to read
+            self.bytes = &[];
+        }
+        note
+    }
+}
+
+impl<'a> NoteIter<'a> {
+    fn parse_next(&mut self) -> Option<ElfNote<'a>> {
+        let namesz = read_u32(self.bytes, 0)? as usize;
+        let descsz = read_u32(self.bytes, 4)? as usize;
+        let note_type = read_u32(self.bytes, 8)?;
+
+        let name_start: usize = 12;
+        let desc_start = align_up(name_start.checked_add(namesz)?, self.align)?;
+        let end = align_up(desc_start.checked_add(descsz)?, self.align)?;
+
+        let mut name = self.bytes.get(name_start..name_start + namesz)?;
+        if name.last() == Some(&0) {
+            name = &name[..name.len() - 1];
+        }
+        let desc = self.bytes.get(desc_start..desc_start + descsz)?;
+
+        self.bytes = self.bytes.get(end..).unwrap_or(&[]);
+        Some(ElfNote { name, note_type, desc })
+    }
+}
+
+/// An iterator over the notes in all of an image's note segments.
+pub struct ElfNoteIter<'a> {
+    image: ElfImage<'a>,
+    phdrs: ElfPhdrIter<'a>,
+    current: Option<NoteIter<'a>>,
+}
+
+impl<'a> Iterator for ElfNoteIter<'a> {
+    type Item = ElfNote<'a>;
+    fn next(&mut self) -> Option<ElfNote<'a>> {
+        loop {
+            if let Some(note) = self.current.as_mut().and_then(|c| c.next()) {
+                return Some(note);
+            }
+            let phdr = self.phdrs.next()?;
+            if phdr.p_type == PT_NOTE {
+                self.current = self.image.segment_bytes(&phdr)
+                    .map(|b| iterate_notes(b, phdr.p_align as usize));
+            }
+        }
+    }
+}
+
+fn align_up(n: usize, align: usize) -> Option<usize> {
+    Some(n.checked_add(align - 1)? & !(align - 1))
+}
+
+/// The class of the ELF file starting with `bytes`, from its `e_ident`, whatever the current
+/// process's class. `None` if it isn't ELF.
+///
+/// This is the one thing here that works on any image, since it's how you find out what some
+/// other process's aux vector looks like.
+pub fn elf_class(bytes: &[u8]) -> Option<ElfClass> {
+    if !bytes.starts_with(ELF_MAGIC) {
+        return None;
+    }
+    match bytes.get(EI_CLASS) {
+        Some(1) => Some(ElfClass::Elf32),
+        Some(2) => Some(ElfClass::Elf64),
+        _ => None
+    }
+}
+
+/// Iterate over the `(d_tag, d_val)` pairs in the contents of a dynamic section, stopping at
+/// `DT_NULL` or the end of `bytes`.
+pub fn iterate_dynamic(bytes: &[u8]) -> DynamicIter<'_> {
+    DynamicIter { bytes }
+}
+
+/// An iterator over `(d_tag, d_val)` pairs in a dynamic section.
+pub struct DynamicIter<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for DynamicIter<'a> {
+    type Item = (usize, usize);
+    fn next(&mut self) -> Option<(usize, usize)> {
+        let tag = read_word(self.bytes, 0)?;
+        let value = read_word(self.bytes, WORD)?;
+        if tag == DT_NULL {
+            self.bytes = &[];
+            return None;
+        }
+        self.bytes = &self.bytes[2 * WORD..];
+        Some((tag, value))
+    }
+}
+
+/// Read a native-endian `u16` at `offset`, if it's in bounds.
+pub(crate) fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    bytes.get(offset..offset.checked_add(2)?).map(NativeEndian::read_u16)
+}
+
+/// Read a native-endian `u32` at `offset`, if it's in bounds.
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes.get(offset..offset.checked_add(4)?).map(NativeEndian::read_u32)
+}
+
+/// Read a native-endian pointer-sized word at `offset`, if it's in bounds.
+pub(crate) fn read_word(bytes: &[u8], offset: usize) -> Option<usize> {
+    let word = bytes.get(offset..offset.checked_add(WORD)?)?;
+    match WORD {
+        4 => Some(NativeEndian::read_u32(word) as usize),
+        _ => Some(NativeEndian::read_u64(word) as usize),
+    }
+}
+
+/// Read the NUL-terminated string starting at `offset`, not including the NUL.
+pub(crate) fn read_cstr(bytes: &[u8], offset: usize) -> Option<&[u8]> {
+    let rest = bytes.get(offset..)?;
+    rest.iter().position(|&b| b == 0).map(|len| &rest[..len])
+}
+
+#[cfg(target_pointer_width="32")]
+fn read_phdr(bytes: &[u8], offset: usize) -> Option<ElfPhdr> {
+    let field = |i: usize| read_u32(bytes, offset.checked_add(4 * i)?);
+    Some(Elf32Phdr {
+        p_type: field(0)?,
+        p_offset: field(1)?,
+        p_vaddr: field(2)?,
+        p_paddr: field(3)?,
+        p_filesz: field(4)?,
+        p_memsz: field(5)?,
+        p_flags: field(6)?,
+        p_align: field(7)?,
+    })
+}
+
+#[cfg(target_pointer_width="64")]
+fn read_phdr(bytes: &[u8], offset: usize) -> Option<ElfPhdr> {
+    let field = |i: usize| read_word(bytes, offset.checked_add(8 + 8 * i)?).map(|w| w as u64);
+    Some(Elf64Phdr {
+        p_type: read_u32(bytes, offset)?,
+        p_flags: read_u32(bytes, offset.checked_add(4)?)?,
+        p_offset: field(0)?,
+        p_vaddr: field(1)?,
+        p_paddr: field(2)?,
+        p_filesz: field(3)?,
+        p_memsz: field(4)?,
+        p_align: field(5)?,
+    })
+}
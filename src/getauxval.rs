@@ -6,18 +6,48 @@
 //! to `getauxval` if it is available and return an appropriate error if it is not. That means it
 //! should be safe to try it if you're not sure your glibc has the function, etc.
 //!
+//! With the default `c-wrapper` feature, `NativeGetauxval` goes through a little C function that
+//! uses a weak symbol, since Rust can't declare weak symbols itself. Without it, it uses
+//! `DlsymGetauxval`, which looks `getauxval` up with `dlsym` at runtime instead and behaves the
+//! same way, so no C compiler is needed to build the crate. `DlsymGetauxval` is available on Linux
+//! either way. Note that `dlsym` can't find anything in a statically linked musl executable, so
+//! there it always reports `FunctionNotAvailable`.
+//!
+//! ## Telling "not found" from 0
+//!
+//! `getauxval` returns 0 for a missing key, so the only way to tell that apart from a key whose
+//! value really is 0 is `errno`: glibc 2.19+, musl and recent Bionic set it to `ENOENT`. Older glibc
+//! and Bionic don't, so there a 0 is ambiguous. `NativeGetauxval` and `DlsymGetauxval` check the
+//! glibc version with `gnu_get_libc_version`, and when a 0 could mean either (old glibc, or a libc
+//! that can't be identified), they look the key up in `/proc/self/auxv` to find out. Without the
+//! `std` feature there's no procfs fallback, and an ambiguous 0 is returned as `Ok(0)`.
+//!
 //! On all OSs, if you want a no-op sort of implementation (for use on non-Linux OSs, etc), you can
 //! use `NotAvailableGetauxval`. It (surprise!) always returns the error that indicates that
 //! `getauxval` function was not found. Of course, you can also use write your own stub
 //! implementation of the trait for testing.
 
+#[cfg(target_os="linux")]
+use std::ffi::{c_char, c_int, c_void, CStr};
+#[cfg(target_os="linux")]
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
 use super::AuxvType;
 
 extern "C" {
     /// Invoke getauxval(3) if available.
-    #[cfg(target_os="linux")]
-    fn getauxval_wrapper(key: AuxvType, success: *mut AuxvType) -> i32;
+    #[cfg(all(target_os="linux", feature = "c-wrapper"))]
+    fn getauxval_wrapper(key: AuxvType, result: *mut AuxvType, error: *mut i32) -> i32;
 }
+
+#[cfg(target_os="linux")]
+extern "C" {
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    fn __errno_location() -> *mut c_int;
+}
+
+#[cfg(target_os="linux")]
+const ENOENT: c_int = 2;
 /// Errors from invoking `getauxval`.
 #[derive(Debug, PartialEq)]
 pub enum GetauxvalError {
@@ -52,20 +82,228 @@ impl Getauxval for NotAvailableGetauxval {
 #[cfg(target_os="linux")]
 pub struct NativeGetauxval {}
 
-#[cfg(target_os="linux")]
+#[cfg(all(target_os="linux", feature = "c-wrapper"))]
 impl Getauxval for NativeGetauxval {
     fn getauxval(&self, key: AuxvType)
                  -> Result<AuxvType, GetauxvalError> {
 
         let mut result = 0;
-        unsafe {
-            return match getauxval_wrapper(key, &mut result) {
-                1 => Ok(result),
-                0 => Err(GetauxvalError::NotFound),
-                -1 => Err(GetauxvalError::FunctionNotAvailable),
-                -2 => Err(GetauxvalError::UnknownError),
+        let mut error = 0;
+        let raw = unsafe {
+            match getauxval_wrapper(key, &mut result, &mut error) {
+                0 => RawGetauxval { value: result, errno: error },
+                -1 => return Err(GetauxvalError::FunctionNotAvailable),
                 x => panic!("getauxval_wrapper returned an unexpected value: {}", x)
             }
+        };
+
+        interpret(key, raw, libc_reports_not_found(), search_procfs)
+    }
+}
+
+#[cfg(all(target_os="linux", not(feature = "c-wrapper")))]
+impl Getauxval for NativeGetauxval {
+    fn getauxval(&self, key: AuxvType)
+                 -> Result<AuxvType, GetauxvalError> {
+        DlsymGetauxval {}.getauxval(key)
+    }
+}
+
+/// Calls through to `getauxval()`, found at runtime with `dlsym`.
+///
+/// This needs no C compiler at build time, but otherwise acts like `NativeGetauxval`.
+#[cfg(target_os="linux")]
+pub struct DlsymGetauxval {}
+
+/// `getauxval`'s address once looked up: 0 if not yet, 1 if it's not there.
+#[cfg(target_os="linux")]
+static GETAUXVAL_ADDR: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(target_os="linux")]
+impl Getauxval for DlsymGetauxval {
+    fn getauxval(&self, key: AuxvType)
+                 -> Result<AuxvType, GetauxvalError> {
+        let addr = resolve(&GETAUXVAL_ADDR, b"getauxval\0").ok_or(GetauxvalError::FunctionNotAvailable)?;
+
+        // same as the C wrapper
+        let raw = unsafe {
+            let getauxval = std::mem::transmute::<usize, unsafe extern "C" fn(AuxvType) -> AuxvType>(addr);
+            let errno = __errno_location();
+            let saved_errno = *errno;
+            *errno = 0;
+            let value = getauxval(key);
+            let error = *errno;
+            *errno = saved_errno;
+            RawGetauxval { value, errno: error }
+        };
+
+        interpret(key, raw, libc_reports_not_found(), search_procfs)
+    }
+}
+
+/// What a call to `getauxval` produced, before deciding what it means.
+#[cfg(target_os="linux")]
+#[derive(Debug, Clone, Copy)]
+struct RawGetauxval {
+    value: AuxvType,
+    /// `errno` after the call, having been cleared before it
+    errno: c_int,
+}
+
+/// Turn a raw `getauxval` result into an answer.
+///
+/// `reports_not_found` says whether the libc sets `ENOENT` for missing keys. If it doesn't, a 0 is
+/// ambiguous, and `search` is used to look `key` up another way: it returns `None` if it couldn't,
+/// or whether the key is there.
+#[cfg(target_os="linux")]
+fn interpret<F>(key: AuxvType, raw: RawGetauxval, reports_not_found: bool, search: F)
+                -> Result<AuxvType, GetauxvalError>
+    where F: FnOnce(AuxvType) -> Option<Option<AuxvType>> {
+    match raw.errno {
+        0 => {}
+        ENOENT => return Err(GetauxvalError::NotFound),
+        _ => return Err(GetauxvalError::UnknownError)
+    }
+
+    if raw.value != 0 || reports_not_found {
+        return Ok(raw.value);
+    }
+
+    match search(key) {
+        Some(Some(value)) => Ok(value),
+        Some(None) => Err(GetauxvalError::NotFound),
+        // nothing else to go on
+        None => Ok(0)
+    }
+}
+
+/// Look `key` up in procfs, for when `getauxval` can't say whether it's there.
+#[cfg(all(target_os="linux", feature = "std"))]
+fn search_procfs(key: AuxvType) -> Option<Option<AuxvType>> {
+    super::procfs::search_procfs_auxv(&[key])
+        .ok()
+        .map(|map| map.get(&key).cloned())
+}
+
+/// Without procfs, there's no other way to look.
+#[cfg(all(target_os="linux", not(feature = "std")))]
+fn search_procfs(_: AuxvType) -> Option<Option<AuxvType>> {
+    None
+}
+
+/// `gnu_get_libc_version`'s address once looked up, like `GETAUXVAL_ADDR`.
+#[cfg(target_os="linux")]
+static GNU_GET_LIBC_VERSION_ADDR: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether the libc sets `ENOENT` for missing keys: 0 if not yet known, 1 if it does, 2 if not.
+#[cfg(target_os="linux")]
+static LIBC_REPORTS_NOT_FOUND: AtomicU8 = AtomicU8::new(0);
+
+/// True if the libc's `getauxval` sets `errno` to `ENOENT` when a key isn't found.
+#[cfg(target_os="linux")]
+fn libc_reports_not_found() -> bool {
+    match LIBC_REPORTS_NOT_FOUND.load(Ordering::Relaxed) {
+        1 => return true,
+        2 => return false,
+        _ => {}
+    }
+
+    let reports = match resolve(&GNU_GET_LIBC_VERSION_ADDR, b"gnu_get_libc_version\0") {
+        Some(addr) => {
+            let version = unsafe {
+                let gnu_get_libc_version =
+                    std::mem::transmute::<usize, unsafe extern "C" fn() -> *const c_char>(addr);
+                CStr::from_ptr(gnu_get_libc_version())
+            };
+            glibc_reports_not_found(version.to_bytes())
         }
+        // musl always has; Bionic only has since 2015, and there's no telling what else this is
+        None => cfg!(target_env="musl")
+    };
+
+    LIBC_REPORTS_NOT_FOUND.store(if reports { 1 } else { 2 }, Ordering::Relaxed);
+    reports
+}
+
+/// True if glibc `version` (e.g. `2.31`) sets `ENOENT`, which it has since 2.19.
+#[cfg(target_os="linux")]
+fn glibc_reports_not_found(version: &[u8]) -> bool {
+    let mut parts = version.split(|&b| b == b'.')
+        .map(|p| std::str::from_utf8(p).ok().and_then(|p| p.parse::<u32>().ok()));
+    match (parts.next(), parts.next()) {
+        (Some(Some(major)), Some(Some(minor))) => (major, minor) >= (2, 19),
+        _ => false
+    }
+}
+
+/// Find `name` (NUL-terminated) with `dlsym`, caching the result in `cache`: 0 if not looked up
+/// yet, 1 if it's not there, or the address.
+#[cfg(target_os="linux")]
+fn resolve(cache: &AtomicUsize, name: &[u8]) -> Option<usize> {
+    let mut addr = cache.load(Ordering::Relaxed);
+    if addr == 0 {
+        // racing lookups all find the same thing, so there's no need to synchronize them
+        let sym = unsafe { dlsym(RTLD_DEFAULT, name.as_ptr() as *const c_char) };
+        addr = if sym.is_null() { 1 } else { sym as usize };
+        cache.store(addr, Ordering::Relaxed);
+    }
+
+    if addr == 1 {
+        None
+    } else {
+        Some(addr)
+    }
+}
+
+/// The pseudo-handle that makes `dlsym` search the global scope.
+#[cfg(target_os="linux")]
+const RTLD_DEFAULT: *mut c_void = std::ptr::null_mut();
+
+#[cfg(all(test, target_os="linux"))]
+mod tests {
+    use super::{glibc_reports_not_found, interpret, GetauxvalError, RawGetauxval, ENOENT};
+
+    #[test]
+    fn test_interpret_found() {
+        let raw = RawGetauxval { value: 4096, errno: 0 };
+        assert_eq!(Ok(4096), interpret(6, raw, false, |_| panic!("no need to search")));
+    }
+
+    #[test]
+    fn test_interpret_enoent_not_found() {
+        let raw = RawGetauxval { value: 0, errno: ENOENT };
+        assert_eq!(Err(GetauxvalError::NotFound), interpret(99, raw, true, |_| None));
+        assert_eq!(Err(GetauxvalError::UnknownError),
+                   interpret(99, RawGetauxval { value: 0, errno: 22 }, true, |_| None));
+    }
+
+    #[test]
+    fn test_interpret_zero_from_new_libc_is_a_value() {
+        // e.g. AT_SECURE, which is usually 0
+        let raw = RawGetauxval { value: 0, errno: 0 };
+        assert_eq!(Ok(0), interpret(23, raw, true, |_| panic!("no need to search")));
+    }
+
+    #[test]
+    fn test_interpret_zero_from_old_libc_searches() {
+        // old glibc and Bionic return 0 without setting errno for both of these
+        let raw = RawGetauxval { value: 0, errno: 0 };
+        assert_eq!(Ok(0), interpret(23, raw, false, |key| {
+            assert_eq!(23, key);
+            Some(Some(0))
+        }));
+        assert_eq!(Err(GetauxvalError::NotFound), interpret(99, raw, false, |_| Some(None)));
+        // can't tell, so report what getauxval said
+        assert_eq!(Ok(0), interpret(99, raw, false, |_| None));
+    }
+
+    #[test]
+    fn test_glibc_reports_not_found() {
+        assert!(!glibc_reports_not_found(b"2.17"));
+        assert!(!glibc_reports_not_found(b"2.18.90"));
+        assert!(glibc_reports_not_found(b"2.19"));
+        assert!(glibc_reports_not_found(b"2.35"));
+        assert!(glibc_reports_not_found(b"3.0"));
+        assert!(!glibc_reports_not_found(b"garbage"));
     }
 }
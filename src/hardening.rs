@@ -0,0 +1,299 @@
+//! Control-flow protection status from GNU properties and `AT_HWCAP`/`AT_HWCAP2`.
+//!
+//! Whether a process actually runs with Intel IBT/SHSTK or Arm BTI/PAC depends on two things:
+//! the executable has to be marked as compatible, and the CPU has to support it. The marking is a
+//! `NT_GNU_PROPERTY_TYPE_0` note whose `GNU_PROPERTY_*_FEATURE_1_AND` property has the relevant
+//! bits set, which the linker only does when every object file in the link was built with the
+//! protection. CPU support, on Arm at least, is in the hwcaps.
+//!
+//! `HardeningReport::current` combines the two for the current process, reading the notes from
+//! memory via the auxv-located program headers. For a captured snapshot,
+//! `HardeningReport::from_snapshot` does the same with properties you provide (e.g. from
+//! `GnuProperties::from_notes` over the executable's file).
+//!
+//! x86 has no hwcap bits for CET, and whether the kernel and libc have enabled shadow stacks is
+//! process state that auxv doesn't describe, so on x86 the CPU side is reported as unknown.
+
+use super::AuxvType;
+use super::elf::{self, ElfNote, NT_GNU_PROPERTY_TYPE_0};
+#[cfg(feature = "std")]
+use super::{AT_HWCAP, AT_HWCAP2};
+#[cfg(feature = "std")]
+use super::layout::ExecutableLayout;
+#[cfg(feature = "std")]
+use super::phdr::ProgramHeaders;
+#[cfg(feature = "std")]
+use super::snapshot::AuxvSnapshot;
+
+/// The x86 feature property, a bitmask of `GNU_PROPERTY_X86_FEATURE_1_*`.
+pub const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xc000_0002;
+/// Compatible with Indirect Branch Tracking
+pub const GNU_PROPERTY_X86_FEATURE_1_IBT: u32 = 1 << 0;
+/// Compatible with Shadow Stacks
+pub const GNU_PROPERTY_X86_FEATURE_1_SHSTK: u32 = 1 << 1;
+/// The AArch64 feature property, a bitmask of `GNU_PROPERTY_AARCH64_FEATURE_1_*`.
+pub const GNU_PROPERTY_AARCH64_FEATURE_1_AND: u32 = 0xc000_0000;
+/// Compatible with Branch Target Identification
+pub const GNU_PROPERTY_AARCH64_FEATURE_1_BTI: u32 = 1 << 0;
+/// Uses Pointer Authentication for return addresses
+pub const GNU_PROPERTY_AARCH64_FEATURE_1_PAC: u32 = 1 << 1;
+
+/// AArch64 `AT_HWCAP` bit for address authentication
+pub const AARCH64_HWCAP_PACA: AuxvType = 1 << 30;
+/// AArch64 `AT_HWCAP2` bit for Branch Target Identification
+pub const AARCH64_HWCAP2_BTI: AuxvType = 1 << 17;
+
+/// The architecture whose hwcap bits and properties to interpret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arch {
+    /// x86 or x86-64
+    X86,
+    /// AArch64
+    Aarch64,
+}
+
+impl Arch {
+    /// The architecture of the current process, if it's one with control-flow protection
+    /// properties.
+    pub fn native() -> Option<Arch> {
+        if cfg!(any(target_arch="x86", target_arch="x86_64")) {
+            Some(Arch::X86)
+        } else if cfg!(target_arch="aarch64") {
+            Some(Arch::Aarch64)
+        } else {
+            None
+        }
+    }
+}
+
+/// The feature properties from an executable's `NT_GNU_PROPERTY_TYPE_0` note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GnuProperties {
+    /// The value of `GNU_PROPERTY_X86_FEATURE_1_AND`, if present
+    pub x86_feature_1_and: Option<u32>,
+    /// The value of `GNU_PROPERTY_AARCH64_FEATURE_1_AND`, if present
+    pub aarch64_feature_1_and: Option<u32>,
+}
+
+impl GnuProperties {
+    /// Find the feature properties among an executable's notes.
+    pub fn from_notes<'a, I: IntoIterator<Item = ElfNote<'a>>>(notes: I) -> GnuProperties {
+        let mut props = GnuProperties::default();
+        let pr_align = std::mem::size_of::<usize>();
+
+        for note in notes {
+            if note.name != b"GNU" || note.note_type != NT_GNU_PROPERTY_TYPE_0 {
+                continue;
+            }
+
+            let mut offset = 0;
+            while let Some((pr_type, value, next)) = read_property(note.desc, offset, pr_align) {
+                match pr_type {
+                    GNU_PROPERTY_X86_FEATURE_1_AND => props.x86_feature_1_and = value,
+                    GNU_PROPERTY_AARCH64_FEATURE_1_AND => props.aarch64_feature_1_and = value,
+                    _ => {}
+                }
+                offset = next;
+            }
+        }
+
+        props
+    }
+}
+
+/// Read the property at `offset` in a property note: its type, its value if it's a `u32`, and the
+/// offset of the next one. `None` at the end, or if the sizes would overflow.
+fn read_property(desc: &[u8], offset: usize, pr_align: usize) -> Option<(u32, Option<u32>, usize)> {
+    // an array of { pr_type: u32, pr_datasz: u32, pr_data, padding }
+    let pr_type = elf::read_u32(desc, offset)?;
+    let pr_datasz = elf::read_u32(desc, offset.checked_add(4)?)?;
+    let data = offset.checked_add(8)?;
+    let value = elf::read_u32(desc, data).filter(|_| pr_datasz == 4);
+    let next = data.checked_add(pr_datasz as usize)?.checked_add(pr_align - 1)? & !(pr_align - 1);
+    Some((pr_type, value, next))
+}
+
+/// The status of one kind of protection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Protection {
+    /// The executable is marked as compatible with (or using) the protection
+    pub marked: bool,
+    /// The CPU supports the protection, or `None` if auxv doesn't say
+    pub cpu_support: Option<bool>,
+}
+
+impl Protection {
+    /// Whether the protection is in effect: `Some(false)` if the executable isn't marked or the
+    /// CPU can't do it, `Some(true)` if it's marked and the CPU can, and `None` if it's marked but
+    /// CPU support is unknown.
+    pub fn is_active(&self) -> Option<bool> {
+        if self.marked {
+            self.cpu_support
+        } else {
+            Some(false)
+        }
+    }
+}
+
+/// Control-flow protection status of a process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HardeningReport {
+    /// Intel Indirect Branch Tracking
+    pub ibt: Protection,
+    /// Intel CET Shadow Stacks
+    pub shstk: Protection,
+    /// Arm Branch Target Identification
+    pub bti: Protection,
+    /// Arm Pointer Authentication
+    pub pac: Protection,
+}
+
+impl HardeningReport {
+    /// Combine an executable's properties with the hwcaps from a snapshot of its auxv.
+    ///
+    /// Protections belonging to an architecture other than `arch` are reported as not marked and
+    /// not supported.
+    #[cfg(feature = "std")]
+    pub fn from_snapshot(snapshot: &AuxvSnapshot, properties: &GnuProperties, arch: Arch)
+                         -> HardeningReport {
+        HardeningReport::from_hwcaps(properties, snapshot.get(AT_HWCAP).unwrap_or(0),
+                                     snapshot.get(AT_HWCAP2).unwrap_or(0), arch)
+    }
+
+    /// Combine an executable's properties with `AT_HWCAP` and `AT_HWCAP2` values; see
+    /// `from_snapshot`.
+    pub fn from_hwcaps(properties: &GnuProperties, hwcap: AuxvType, hwcap2: AuxvType, arch: Arch)
+                       -> HardeningReport {
+        let none = Protection { marked: false, cpu_support: Some(false) };
+
+        match arch {
+            Arch::X86 => {
+                let x86 = properties.x86_feature_1_and.unwrap_or(0);
+                HardeningReport {
+                    ibt: Protection {
+                        marked: x86 & GNU_PROPERTY_X86_FEATURE_1_IBT != 0,
+                        cpu_support: None,
+                    },
+                    shstk: Protection {
+                        marked: x86 & GNU_PROPERTY_X86_FEATURE_1_SHSTK != 0,
+                        cpu_support: None,
+                    },
+                    bti: none,
+                    pac: none,
+                }
+            }
+            Arch::Aarch64 => {
+                let aarch64 = properties.aarch64_feature_1_and.unwrap_or(0);
+                HardeningReport {
+                    ibt: none,
+                    shstk: none,
+                    bti: Protection {
+                        marked: aarch64 & GNU_PROPERTY_AARCH64_FEATURE_1_BTI != 0,
+                        cpu_support: Some(hwcap2 & AARCH64_HWCAP2_BTI != 0),
+                    },
+                    pac: Protection {
+                        marked: aarch64 & GNU_PROPERTY_AARCH64_FEATURE_1_PAC != 0,
+                        cpu_support: Some(hwcap & AARCH64_HWCAP_PACA != 0),
+                    },
+                }
+            }
+        }
+    }
+
+    /// The report for the current process, or `None` if its architecture has no control-flow
+    /// protection properties or its program headers can't be found.
+    #[cfg(feature = "std")]
+    pub fn current() -> Option<HardeningReport> {
+        let arch = Arch::native()?;
+        let headers = ProgramHeaders::current().ok()?;
+        let layout = ExecutableLayout::current().ok()?;
+        let properties = unsafe { GnuProperties::from_notes(layout.notes(headers)) };
+
+        let snapshot: AuxvSnapshot = [AT_HWCAP, AT_HWCAP2].iter()
+            .filter_map(|&key| super::current_auxv_value(key).map(|value| super::AuxvPair { key, value }))
+            .collect();
+
+        Some(HardeningReport::from_snapshot(&snapshot, &properties, arch))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::{Arch, GnuProperties, HardeningReport, Protection, AARCH64_HWCAP2_BTI,
+                AARCH64_HWCAP_PACA, GNU_PROPERTY_AARCH64_FEATURE_1_AND,
+                GNU_PROPERTY_X86_FEATURE_1_AND};
+    use super::super::{AuxvPair, AT_HWCAP, AT_HWCAP2};
+    use super::super::elf::{ElfNote, NT_GNU_PROPERTY_TYPE_0};
+    use super::super::snapshot::AuxvSnapshot;
+
+    /// Encode properties the way the linker does, padded to the word size.
+    fn property_desc(props: &[(u32, u32)]) -> Vec<u8> {
+        let mut desc = Vec::new();
+        for &(pr_type, value) in props {
+            desc.extend_from_slice(&pr_type.to_ne_bytes());
+            desc.extend_from_slice(&4u32.to_ne_bytes());
+            desc.extend_from_slice(&value.to_ne_bytes());
+            while desc.len() % std::mem::size_of::<usize>() != 0 {
+                desc.push(0);
+            }
+        }
+        desc
+    }
+
+    #[test]
+    fn test_gnu_properties_from_notes() {
+        // an unrelated property first, to check that we skip over it
+        let desc = property_desc(&[(0xc000_8002, 7), (GNU_PROPERTY_X86_FEATURE_1_AND, 3)]);
+        let notes = vec![
+            ElfNote { name: b"GNU", note_type: 3, desc: &[1, 2, 3, 4] },
+            ElfNote { name: b"GNU", note_type: NT_GNU_PROPERTY_TYPE_0, desc: &desc },
+        ];
+
+        assert_eq!(GnuProperties { x86_feature_1_and: Some(3), aarch64_feature_1_and: None },
+                   GnuProperties::from_notes(notes));
+    }
+
+    #[test]
+    fn test_gnu_properties_bogus_datasz_stops() {
+        let mut desc = property_desc(&[(GNU_PROPERTY_X86_FEATURE_1_AND, 3)]);
+        // a pr_datasz that runs off the end, and past the end of a 32-bit address space
+        desc.extend_from_slice(&GNU_PROPERTY_AARCH64_FEATURE_1_AND.to_ne_bytes());
+        desc.extend_from_slice(&u32::MAX.to_ne_bytes());
+        desc.extend_from_slice(&[0; 8]);
+        let notes = vec![ElfNote { name: b"GNU", note_type: NT_GNU_PROPERTY_TYPE_0, desc: &desc }];
+
+        assert_eq!(GnuProperties { x86_feature_1_and: Some(3), aarch64_feature_1_and: None },
+                   GnuProperties::from_notes(notes));
+    }
+
+    #[test]
+    fn test_report_aarch64_bti_marked_cpu_without_bti() {
+        let desc = property_desc(&[(GNU_PROPERTY_AARCH64_FEATURE_1_AND, 3)]);
+        let properties = GnuProperties::from_notes(
+            vec![ElfNote { name: b"GNU", note_type: NT_GNU_PROPERTY_TYPE_0, desc: &desc }]);
+        let snapshot = AuxvSnapshot::new(vec![
+            AuxvPair { key: AT_HWCAP, value: AARCH64_HWCAP_PACA },
+            AuxvPair { key: AT_HWCAP2, value: 0 },
+        ]);
+
+        let report = HardeningReport::from_snapshot(&snapshot, &properties, Arch::Aarch64);
+        assert_eq!(Protection { marked: true, cpu_support: Some(false) }, report.bti);
+        assert_eq!(Some(false), report.bti.is_active());
+        assert_eq!(Some(true), report.pac.is_active());
+        assert_eq!(Some(false), report.shstk.is_active());
+
+        let snapshot = AuxvSnapshot::new(vec![AuxvPair { key: AT_HWCAP2, value: AARCH64_HWCAP2_BTI }]);
+        let report = HardeningReport::from_snapshot(&snapshot, &properties, Arch::Aarch64);
+        assert_eq!(Some(true), report.bti.is_active());
+    }
+
+    #[test]
+    fn test_report_x86_cpu_support_unknown() {
+        let properties = GnuProperties { x86_feature_1_and: Some(2), aarch64_feature_1_and: None };
+
+        let report = HardeningReport::from_snapshot(&AuxvSnapshot::default(), &properties, Arch::X86);
+        assert_eq!(Some(false), report.ibt.is_active());
+        assert_eq!(Protection { marked: true, cpu_support: None }, report.shstk);
+        assert_eq!(None, report.shstk.is_active());
+    }
+}
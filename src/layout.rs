@@ -0,0 +1,294 @@
+//! Where the main executable is loaded, derived from auxv and the program headers.
+//!
+//! Symbolizing an address in the main executable needs its load bias: the difference between
+//! runtime addresses and the addresses in the ELF file. The kernel doesn't hand that over
+//! directly, but it can be computed the same way the dynamic linker does it: `AT_PHDR` is the
+//! runtime address of the program headers, and the `PT_PHDR` header says where they are in the
+//! file, so the bias is the difference. `AT_BASE` says whether an interpreter (the dynamic
+//! linker) was loaded: it's 0 for a statically linked executable.
+//!
+//! `ExecutableLayout` collects all that into one report, without touching `/proc/self/maps`.
+//!
+//! Once the bias is known, the executable's segments can be read straight out of memory. In
+//! particular, `current_build_id` finds the GNU build ID in the `PT_NOTE` segments that way, with
+//! no file I/O, so it works even if the executable has been deleted or replaced on disk.
+
+use super::{AuxvPair, AT_BASE, AT_ENTRY, AT_PHDR};
+use std::slice;
+
+use super::elf::{iterate_notes, ElfNote, ElfPhdr, NoteIter, EHDR_SIZE, NT_GNU_BUILD_ID, PT_LOAD,
+                 PT_NOTE, PT_PHDR};
+use super::phdr::{PhdrError, ProgramHeaders};
+use super::snapshot::AuxvSnapshot;
+
+/// Errors from computing an executable's layout.
+#[derive(Debug, PartialEq)]
+pub enum LayoutError {
+    /// the auxv entries or program headers needed are missing
+    NotAvailable,
+    /// the program headers are inconsistent with auxv
+    InvalidFormat
+}
+
+/// The runtime layout of a process's main executable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecutableLayout {
+    /// Runtime address minus the address in the executable's headers. 0 for non-PIE executables.
+    pub load_bias: usize,
+    /// True if the executable is position-independent, i.e. its segments are linked at 0 and it
+    /// can be loaded anywhere.
+    pub is_pie: bool,
+    /// The runtime address of the entry point (`AT_ENTRY`)
+    pub entry: usize,
+    /// The load address of the interpreter (`AT_BASE`), or `None` if the kernel started the
+    /// executable directly because it's statically linked.
+    pub interpreter_base: Option<usize>,
+    /// The runtime address of the start of the lowest `PT_LOAD` segment
+    pub start: usize,
+    /// The runtime address just past the end of the highest `PT_LOAD` segment
+    pub end: usize,
+}
+
+impl ExecutableLayout {
+    /// Compute the layout from a snapshot of a process's auxv and that process's program headers.
+    ///
+    /// The headers don't have to come from the process's memory; a copy read from anywhere will do,
+    /// as long as it's the same executable.
+    ///
+    /// If there is no `PT_PHDR` header (common in non-PIE static executables), the program headers
+    /// are assumed to immediately follow the ELF header at the start of the first `PT_LOAD`
+    /// segment, which is where every mainstream linker puts them.
+    pub fn from_parts(snapshot: &AuxvSnapshot, headers: ProgramHeaders)
+                      -> Result<ExecutableLayout, LayoutError> {
+        let phdr = snapshot.get(AT_PHDR).ok_or(LayoutError::NotAvailable)? as usize;
+        let entry = snapshot.get(AT_ENTRY).ok_or(LayoutError::NotAvailable)? as usize;
+
+        let (start, end) = headers.iter()
+            .filter(|p| p.p_type == PT_LOAD)
+            .fold(None, |range, p| {
+                let (s, e) = (p.p_vaddr as usize, p.p_vaddr.saturating_add(p.p_memsz) as usize);
+                Some(match range {
+                    Some((start, end)) => (std::cmp::min(start, s), std::cmp::max(end, e)),
+                    None => (s, e)
+                })
+            })
+            .ok_or(LayoutError::NotAvailable)?;
+
+        let phdr_vaddr = match headers.find(PT_PHDR) {
+            Some(p) => p.p_vaddr as usize,
+            None => headers.iter()
+                .find(|p| p.p_type == PT_LOAD && p.p_offset == 0)
+                .map(|p| p.p_vaddr as usize + EHDR_SIZE)
+                .ok_or(LayoutError::NotAvailable)?
+        };
+        let load_bias = phdr.checked_sub(phdr_vaddr).ok_or(LayoutError::InvalidFormat)?;
+
+        let layout = ExecutableLayout {
+            load_bias,
+            is_pie: start == 0,
+            entry,
+            interpreter_base: match snapshot.get(AT_BASE) {
+                Some(0) | None => None,
+                Some(base) => Some(base as usize)
+            },
+            start: start.checked_add(load_bias).ok_or(LayoutError::InvalidFormat)?,
+            end: end.checked_add(load_bias).ok_or(LayoutError::InvalidFormat)?,
+        };
+
+        // a sanity check that the bias makes sense
+        if !layout.contains(entry) {
+            return Err(LayoutError::InvalidFormat);
+        }
+
+        Ok(layout)
+    }
+
+    /// Compute the layout from a snapshot of the current process's auxv, reading the program
+    /// headers from memory.
+    ///
+    /// # Safety
+    ///
+    /// The snapshot must be of the current process; see `ProgramHeaders::from_snapshot`.
+    pub unsafe fn from_snapshot(snapshot: &AuxvSnapshot) -> Result<ExecutableLayout, LayoutError> {
+        let headers = ProgramHeaders::from_snapshot(snapshot).map_err(map_phdr_err)?;
+        ExecutableLayout::from_parts(snapshot, headers)
+    }
+
+    /// The layout of the current process's main executable.
+    pub fn current() -> Result<ExecutableLayout, LayoutError> {
+        let headers = ProgramHeaders::current().map_err(map_phdr_err)?;
+        let snapshot: AuxvSnapshot = [AT_PHDR, AT_ENTRY, AT_BASE].iter()
+            .filter_map(|&key| super::current_auxv_value(key).map(|value| AuxvPair { key, value }))
+            .collect();
+        ExecutableLayout::from_parts(&snapshot, headers)
+    }
+
+    /// Iterate over the notes in the executable's `PT_NOTE` segments, reading them from memory.
+    ///
+    /// # Safety
+    ///
+    /// `headers` and this layout must describe the current process's main executable.
+    pub unsafe fn notes<'a>(&self, headers: ProgramHeaders<'a>) -> MappedNoteIter<'a> {
+        MappedNoteIter {
+            headers: headers.iter(),
+            load_bias: self.load_bias,
+            current: None,
+        }
+    }
+
+    /// True if the executable was started without an interpreter.
+    pub fn is_static(&self) -> bool {
+        self.interpreter_base.is_none()
+    }
+
+    /// True if `addr` is within the executable's loaded segments.
+    pub fn contains(&self, addr: usize) -> bool {
+        addr >= self.start && addr < self.end
+    }
+
+    /// Translate a runtime address to the corresponding address in the executable's headers and
+    /// symbol tables.
+    pub fn to_file_address(&self, addr: usize) -> usize {
+        addr.wrapping_sub(self.load_bias)
+    }
+
+    /// Translate an address from the executable's headers or symbol tables to its runtime
+    /// address.
+    pub fn to_runtime_address(&self, addr: usize) -> usize {
+        addr.wrapping_add(self.load_bias)
+    }
+}
+
+/// The GNU build ID of the current process's main executable, read from memory.
+///
+/// Returns `None` if the executable has no build ID note, or its program headers can't be found.
+pub fn current_build_id() -> Option<&'static [u8]> {
+    let headers = ProgramHeaders::current().ok()?;
+    let layout = ExecutableLayout::current().ok()?;
+
+    unsafe {
+        layout.notes(headers)
+            .find(|n| n.name == b"GNU" && n.note_type == NT_GNU_BUILD_ID)
+            .map(|n| n.desc)
+    }
+}
+
+/// An iterator over the notes in an executable's `PT_NOTE` segments in memory.
+pub struct MappedNoteIter<'a> {
+    headers: slice::Iter<'a, ElfPhdr>,
+    load_bias: usize,
+    current: Option<NoteIter<'a>>,
+}
+
+impl<'a> Iterator for MappedNoteIter<'a> {
+    type Item = ElfNote<'a>;
+    fn next(&mut self) -> Option<ElfNote<'a>> {
+        loop {
+            if let Some(note) = self.current.as_mut().and_then(|c| c.next()) {
+                return Some(note);
+            }
+            let phdr = self.headers.next()?;
+            if phdr.p_type == PT_NOTE {
+                // the caller of `notes` promised these are mapped
+                let bytes = unsafe {
+                    slice::from_raw_parts((phdr.p_vaddr as usize).wrapping_add(self.load_bias) as *const u8,
+                                          phdr.p_filesz as usize)
+                };
+                self.current = Some(iterate_notes(bytes, phdr.p_align as usize));
+            }
+        }
+    }
+}
+
+fn map_phdr_err(e: PhdrError) -> LayoutError {
+    match e {
+        PhdrError::NotAvailable => LayoutError::NotAvailable,
+        PhdrError::InvalidFormat => LayoutError::InvalidFormat
+    }
+}
+
+#[cfg(all(test, target_pointer_width="64"))]
+mod tests {
+    extern crate byteorder;
+
+    use self::byteorder::LittleEndian;
+
+    use super::{ExecutableLayout, LayoutError};
+    use super::super::{AuxvPair, AT_BASE, AT_ENTRY, AT_PHDR};
+    use super::super::bytes::iterate_bytes_auxv;
+    use super::super::elf::{ElfPhdr, PT_INTERP, PT_LOAD, PT_PHDR};
+    use super::super::phdr::ProgramHeaders;
+    use super::super::snapshot::AuxvSnapshot;
+    use super::super::test_support::read_fixture;
+
+    fn phdr(p_type: u32, p_offset: u64, p_vaddr: u64, p_memsz: u64) -> ElfPhdr {
+        ElfPhdr {
+            p_type,
+            p_flags: 0,
+            p_offset,
+            p_vaddr,
+            p_paddr: p_vaddr,
+            p_filesz: p_memsz,
+            p_memsz,
+            p_align: 0x1000,
+        }
+    }
+
+    fn fixture_snapshot() -> AuxvSnapshot {
+        iterate_bytes_auxv::<LittleEndian>(&read_fixture("linux-x64-i7-6850k.auxv")).map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_layout_real_linux_x64_non_pie() {
+        // AT_PHDR is 0x400040 and AT_ENTRY 0x402660 in this fixture: a classic non-PIE executable
+        let headers = [
+            phdr(PT_PHDR, 0x40, 0x40_0040, 0x230),
+            phdr(PT_INTERP, 0x270, 0x40_0270, 0x1c),
+            phdr(PT_LOAD, 0, 0x40_0000, 0x8000),
+            phdr(PT_LOAD, 0x8000, 0x60_8000, 0x1000),
+        ];
+
+        let layout = ExecutableLayout::from_parts(&fixture_snapshot(), ProgramHeaders::new(&headers))
+            .unwrap();
+        assert_eq!(ExecutableLayout {
+            load_bias: 0,
+            is_pie: false,
+            entry: 4204128,
+            interpreter_base: Some(139881368498176),
+            start: 0x40_0000,
+            end: 0x60_9000,
+        }, layout);
+        assert!(!layout.is_static());
+    }
+
+    #[test]
+    fn test_layout_static_pie_without_pt_phdr() {
+        let snapshot = AuxvSnapshot::new(vec![
+            AuxvPair { key: AT_PHDR, value: 0x5555_0000_0040 },
+            AuxvPair { key: AT_ENTRY, value: 0x5555_0000_1234 },
+            AuxvPair { key: AT_BASE, value: 0 },
+        ]);
+        let headers = [
+            phdr(PT_LOAD, 0, 0, 0x1000),
+            phdr(PT_LOAD, 0x1000, 0x1000, 0x4000),
+        ];
+
+        let layout = ExecutableLayout::from_parts(&snapshot, ProgramHeaders::new(&headers)).unwrap();
+        assert_eq!(0x5555_0000_0000, layout.load_bias);
+        assert!(layout.is_pie);
+        assert!(layout.is_static());
+        assert_eq!(0x1234, layout.to_file_address(0x5555_0000_1234));
+    }
+
+    #[test]
+    fn test_layout_entry_outside_segments_invalidformat() {
+        let snapshot = AuxvSnapshot::new(vec![
+            AuxvPair { key: AT_PHDR, value: 0x5555_0000_0040 },
+            AuxvPair { key: AT_ENTRY, value: 0x1234 },
+        ]);
+        let headers = [phdr(PT_PHDR, 0x40, 0x40, 0x38), phdr(PT_LOAD, 0, 0, 0x1000)];
+
+        assert_eq!(LayoutError::InvalidFormat,
+                   ExecutableLayout::from_parts(&snapshot, ProgramHeaders::new(&headers)).unwrap_err());
+    }
+}
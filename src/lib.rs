@@ -51,6 +51,9 @@
 //!   OS, but it is `unsafe` and only is possible if the environment has not been modified since
 //!   the process started.
 //!
+//! - Sometimes the aux vector has already been captured elsewhere, like in a Breakpad or Crashpad
+//!   minidump. The `minidump` and `bytes` modules handle that case.
+//!
 //! This library lets you use all of these options, so chances are pretty good that at least one of
 //! them will work in any given host. See each submodule for details on how and when to use it.
 //!
@@ -58,12 +61,31 @@
 //! procfs way if `getauxval` is not available at runtime. You should only try the stack crawling
 //! way if you are sure that it is safe; see its docs for details.
 //!
+//! If you look up auxv values often, the `cache` module reads the whole aux vector once, from
+//! whichever of these works, and answers lookups from memory after that. To look up a handful of
+//! keys from any of the iterators without allocating, use `search_auxv` or `try_search_auxv`.
+//! To read the aux vector of every process on the system, see the `scan` module.
+//!
 //! See the `examples` dir for examples of each way of accessing auxv.
 //!
 //! ## Auxv type width
 //!
 //! `AuxvType` is selected at compile time to be either `u32` or `u64` depending on the pointer
 //! width of the system. This type is used for the key and value.
+//!
+//! ## `no_std`
+//!
+//! The `std` feature is on by default. Without it, the crate is `#![no_std]` and doesn't need an
+//! allocator either: what's left is the types and `AT_` constants, the `bytes` and `elf` parsers,
+//! `Vdso` parsing, the `hardening` decoders, `getauxval` and the `stack` iterators. Everything
+//! that reads files, builds collections or finds things in the current process on its own
+//! (`procfs`, `minidump`, `snapshot`, `current_vdso`, ...) needs `std`.
+
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
+
+// so that `std::` paths to things that are in core work either way
+#[cfg(all(not(feature = "std"), not(test)))]
+extern crate core as std;
 
 // The key/value pairs in auxv are either Elf32_auxv_t or Elf64_auxv_t.
 // If this is an LP64 system (a "long" is 64 bits) then it seems that
@@ -80,18 +102,190 @@ pub type AuxvType = u32;
 #[cfg(target_pointer_width="64")]
 pub type AuxvType = u64;
 
+/// The address of the main executable's program headers.
+pub const AT_PHDR: AuxvType = 3;
+/// The size of one program header entry.
+pub const AT_PHENT: AuxvType = 4;
+/// The number of program headers.
+pub const AT_PHNUM: AuxvType = 5;
+/// The load address of the program interpreter, or 0 if there isn't one.
+pub const AT_BASE: AuxvType = 7;
+/// The entry point of the main executable.
+pub const AT_ENTRY: AuxvType = 9;
+
 // from [linux]/include/uapi/linux/auxvec.h. First 32 bits of HWCAP
 // even on platforms where unsigned long is 64 bits.
 pub const AT_HWCAP: AuxvType = 16;
 pub const AT_HWCAP2: AuxvType = 26;
+/// Nonzero if the process runs with more privilege than whoever ran it, e.g. setuid or setgid, or
+/// with file capabilities.
+pub const AT_SECURE: AuxvType = 23;
+/// The address of the filename the executable was run as, a NUL-terminated string.
+pub const AT_EXECFN: AuxvType = 31;
+/// The address of the vDSO's ELF header.
+pub const AT_SYSINFO_EHDR: AuxvType = 33;
 
 /// An auxv key-value pair.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct AuxvPair {
     pub key: AuxvType,
     pub value: AuxvType,
 }
 
+/// Look up several keys in one pass over `pairs`, without allocating.
+///
+/// `pairs` can come from any source: `iterate_stack_auxv`, `AuxvSnapshot::pairs`, etc. Each
+/// element of the result is the value of the key at the same index in `keys`, or `None` if it
+/// wasn't there. If a key appears more than once, the first one wins, and iteration stops as soon
+/// as every key has been found.
+pub fn search_auxv<I, const N: usize>(pairs: I, keys: [AuxvType; N]) -> [Option<AuxvType>; N]
+    where I: IntoIterator<Item = AuxvPair> {
+    match try_search_auxv::<_, std::convert::Infallible, N>(pairs.into_iter().map(Ok), keys) {
+        Ok(values) => values,
+        Err(never) => match never {}
+    }
+}
+
+/// Like `search_auxv`, for sources whose items are `Result`s, like the procfs and bytes
+/// iterators. Returns the first error, if there is one before every key has been found.
+pub fn try_search_auxv<I, E, const N: usize>(pairs: I, keys: [AuxvType; N])
+                                             -> Result<[Option<AuxvType>; N], E>
+    where I: IntoIterator<Item = Result<AuxvPair, E>> {
+    let mut values = [None; N];
+    let mut remaining = N;
+
+    for pair in pairs {
+        if remaining == 0 {
+            break;
+        }
+        let pair = pair?;
+
+        for (key, value) in keys.iter().zip(values.iter_mut()) {
+            if *key == pair.key && value.is_none() {
+                *value = Some(pair.value);
+                remaining -= 1;
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+/// The ELF class of a process, which determines how wide its auxv keys and values are.
+///
+/// This only matters when reading auxv data that didn't come from the current process (e.g. a
+/// 32-bit process's `/proc/<pid>/auxv` read on a 64-bit host, or a minidump), since the current
+/// process's auxv always matches `AuxvType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfClass {
+    /// 32-bit keys and values (`Elf32_auxv_t`)
+    Elf32,
+    /// 64-bit keys and values (`Elf64_auxv_t`)
+    Elf64,
+}
+
+impl ElfClass {
+    /// The class matching `AuxvType` on this system.
+    pub fn native() -> ElfClass {
+        match std::mem::size_of::<AuxvType>() {
+            4 => ElfClass::Elf32,
+            _ => ElfClass::Elf64,
+        }
+    }
+
+    /// The width in bytes of a single key or value.
+    pub fn word_size(self) -> usize {
+        match self {
+            ElfClass::Elf32 => 4,
+            ElfClass::Elf64 => 8,
+        }
+    }
+}
+
+/// Look up `key` in the current process's auxv, using the cached copy if one could be read, and
+/// `getauxval` otherwise.
+#[cfg(feature = "std")]
+fn current_auxv_value(key: AuxvType) -> Option<AuxvType> {
+    let table = cache::cached_auxv();
+    if table.source().is_some() {
+        return table.get(key);
+    }
+
+    #[cfg(target_os="linux")]
+    {
+        use getauxval::{Getauxval, GetauxvalError, NativeGetauxval};
+        let native_getauxval = NativeGetauxval {};
+        match native_getauxval.getauxval(key) {
+            Ok(v) => return Some(v),
+            Err(GetauxvalError::NotFound) => return None,
+            Err(_) => {}
+        }
+    }
+
+    // the cache already tried procfs
+    None
+}
+
+pub mod bytes;
+#[cfg(feature = "std")]
+pub mod cache;
+pub mod elf;
 pub mod getauxval;
+pub mod hardening;
+#[cfg(feature = "std")]
+pub mod layout;
+#[cfg(feature = "std")]
+pub mod linkmap;
+#[cfg(feature = "std")]
+pub mod minidump;
+#[cfg(feature = "std")]
+pub mod phdr;
+#[cfg(feature = "std")]
 pub mod procfs;
+#[cfg(feature = "std")]
+pub mod scan;
+#[cfg(target_os="linux")]
+pub mod signal_safe;
+#[cfg(feature = "std")]
+pub mod snapshot;
 pub mod stack;
+pub mod vdso;
+
+#[cfg(test)]
+mod test_support;
+
+#[cfg(test)]
+mod tests {
+    use super::{search_auxv, try_search_auxv, AuxvPair, AuxvType};
+
+    fn pairs() -> Vec<AuxvPair> {
+        vec![AuxvPair { key: 6, value: 4096 }, AuxvPair { key: 16, value: 0xff },
+             AuxvPair { key: 23, value: 0 }, AuxvPair { key: 6, value: 8192 }]
+    }
+
+    #[test]
+    fn test_search_auxv() {
+        assert_eq!([Some(0xff), None, Some(4096), Some(0)],
+                   search_auxv(pairs(), [16, 99, 6, 23]));
+    }
+
+    #[test]
+    fn test_search_auxv_repeated_key() {
+        assert_eq!([Some(4096), Some(4096)], search_auxv(pairs(), [6, 6]));
+    }
+
+    #[test]
+    fn test_search_auxv_no_keys() {
+        assert_eq!([] as [Option<AuxvType>; 0], search_auxv(pairs(), []));
+    }
+
+    #[test]
+    fn test_try_search_auxv_error() {
+        let results = vec![Ok(AuxvPair { key: 6, value: 4096 }), Err("oops"),
+                           Ok(AuxvPair { key: 16, value: 0xff })];
+
+        assert_eq!(Err("oops"), try_search_auxv(results.clone(), [6, 16]));
+        // found everything before the error
+        assert_eq!(Ok([Some(4096)]), try_search_auxv(results, [6]));
+    }
+}
@@ -0,0 +1,434 @@
+//! The shared objects loaded into the current process, found without libc's help.
+//!
+//! `dl_iterate_phdr` is the usual way to list the executable, the libraries it loaded, and the
+//! vDSO, but it's a libc function: it's not there before libc is initialized, in a program that
+//! doesn't link libc, or in a libc that doesn't provide it. All it does, though, is walk data
+//! structures that are reachable from auxv:
+//!
+//! - `AT_PHDR` leads to the executable's program headers, and from there to its `PT_DYNAMIC`
+//!   segment.
+//! - The dynamic linker fills in the `DT_DEBUG` entry of that segment with the address of its
+//!   `r_debug` structure (this is how debuggers find loaded libraries), which points to the head of
+//!   the `link_map` list: one entry per loaded object, with its name, load bias and the address of
+//!   its dynamic section.
+//! - An object's ELF header is at its load bias plus the address of its first segment, which is 0
+//!   for nearly every shared object, so the load bias is tried first, and checked against the
+//!   object's dynamic section. For the rest (e.g. prelinked libraries), the header is found as the
+//!   start of the file mapping (in `/proc/self/maps`) that holds the dynamic section.
+//! - `AT_BASE` is where the kernel loaded the interpreter, and `AT_SYSINFO_EHDR` is where it mapped
+//!   the vDSO. glibc puts both in the `link_map` list too, but a statically linked executable has
+//!   no list, so they're also added directly.
+//!
+//! `loaded_objects` does all that for the current process. Objects are listed in the order the
+//! dynamic linker loaded them, starting with the executable, and each one only once.
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::slice;
+
+use super::{AuxvPair, AT_BASE, AT_ENTRY, AT_EXECFN, AT_PHDR, AT_PHENT, AT_PHNUM, AT_SYSINFO_EHDR};
+use super::elf::{iterate_dynamic, DT_DEBUG, PT_LOAD};
+use super::layout::{ExecutableLayout, LayoutError};
+use super::phdr::{PhdrError, ProgramHeaders};
+use super::procfs::{MemoryMapping, Procfs, Target};
+use super::snapshot::AuxvSnapshot;
+
+/// The name given to the vDSO when the dynamic linker hasn't named it.
+pub const VDSO_NAME: &[u8] = b"[vdso]";
+
+/// `link_map` lists longer than this are assumed to be corrupt (e.g. circular).
+const MAX_LINK_MAP_ENTRIES: usize = 4096;
+
+/// Errors from enumerating loaded objects.
+#[derive(Debug, PartialEq)]
+pub enum LinkMapError {
+    /// the auxv entries or program headers needed to find the executable are missing
+    NotAvailable,
+    /// the executable's program headers are inconsistent with auxv
+    InvalidFormat,
+    /// a `link_map` object's ELF header isn't at its load bias, and couldn't be found via
+    /// `/proc/self/maps` either
+    HeaderNotFound
+}
+
+/// An object mapped into the current process.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadedObject<'a> {
+    /// The name the dynamic linker recorded for the object, which is usually its path. For the
+    /// executable it's `AT_EXECFN`, and for the vDSO it's `VDSO_NAME` unless the dynamic linker
+    /// named it. Empty if unknown.
+    pub name: &'a [u8],
+    /// Runtime address minus the address in the object's headers, as in `dlpi_addr`
+    pub base: usize,
+    /// The object's program headers
+    pub headers: ProgramHeaders<'a>,
+}
+
+/// The start of `struct r_debug`, which is the same in glibc and musl.
+#[repr(C)]
+struct RDebug {
+    r_version: i32,
+    r_map: *const LinkMap,
+}
+
+/// The public part of `struct link_map`; the dynamic linker's private fields follow it.
+#[repr(C)]
+struct LinkMap {
+    l_addr: usize,
+    l_name: *const c_char,
+    l_ld: usize,
+    l_next: *const LinkMap,
+    l_prev: *const LinkMap,
+}
+
+/// List the objects loaded into the current process.
+///
+/// # Safety
+///
+/// No other thread may be loading or unloading libraries (`dlopen`, `dlclose`) while the
+/// `link_map` list is walked, since nothing here takes the dynamic linker's lock.
+pub unsafe fn loaded_objects() -> Result<Vec<LoadedObject<'static>>, LinkMapError> {
+    let snapshot: AuxvSnapshot =
+        [AT_PHDR, AT_PHENT, AT_PHNUM, AT_ENTRY, AT_BASE, AT_EXECFN, AT_SYSINFO_EHDR].iter()
+            .filter_map(|&key| super::current_auxv_value(key).map(|value| AuxvPair { key, value }))
+            .collect();
+    loaded_objects_from_snapshot(&snapshot)
+}
+
+/// List the objects loaded into the current process, starting from a snapshot of its auxv.
+///
+/// `/proc/self/maps` is only read for objects whose ELF header isn't at their load bias; if it's
+/// needed and can't be read, or doesn't lead to the header either, that's `HeaderNotFound`.
+///
+/// # Safety
+///
+/// The snapshot must be of the current process's auxv, and see `loaded_objects`.
+pub unsafe fn loaded_objects_from_snapshot(snapshot: &AuxvSnapshot)
+                                           -> Result<Vec<LoadedObject<'static>>, LinkMapError> {
+    let headers = ProgramHeaders::from_snapshot(snapshot).map_err(|e| match e {
+        PhdrError::NotAvailable => LinkMapError::NotAvailable,
+        PhdrError::InvalidFormat => LinkMapError::InvalidFormat
+    })?;
+    let layout = ExecutableLayout::from_parts(snapshot, headers).map_err(|e| match e {
+        LayoutError::NotAvailable => LinkMapError::NotAvailable,
+        LayoutError::InvalidFormat => LinkMapError::InvalidFormat
+    })?;
+
+    let mut objects = vec![LoadedObject {
+        name: snapshot.get(AT_EXECFN).map(|p| c_str(p as usize)).unwrap_or(b""),
+        base: layout.load_bias,
+        headers,
+    }];
+    let mut mappings = None;
+    for (name, l_addr, l_ld) in link_map_entries(headers, layout.load_bias) {
+        // the dynamic linker's list starts with the executable, which is already there
+        if objects.iter().any(|o| o.base == l_addr) {
+            continue;
+        }
+        let object = match object_at_load_bias(name, l_addr, l_ld) {
+            Some(object) => object,
+            None => {
+                if mappings.is_none() {
+                    let read = Procfs::new().read_maps(Target::Current)
+                        .map_err(|_| LinkMapError::HeaderNotFound)?;
+                    mappings = Some(read);
+                }
+                mappings.as_ref()
+                    .and_then(|mappings| ehdr_for_dynamic(mappings, l_ld))
+                    .and_then(|ehdr| object_at(name, ehdr))
+                    .filter(|o| o.base == l_addr)
+                    .ok_or(LinkMapError::HeaderNotFound)?
+            }
+        };
+        objects.push(object);
+    }
+
+    let mut add = |object: Option<LoadedObject<'static>>| {
+        if let Some(object) = object {
+            if objects.iter().all(|o| o.base != object.base) {
+                objects.push(object);
+            }
+        }
+    };
+
+    if let Some(interpreter_base) = layout.interpreter_base {
+        let name = headers.interp()
+            .map(|p| c_str((p.p_vaddr as usize).wrapping_add(layout.load_bias)))
+            .unwrap_or(b"");
+        add(object_at(name, interpreter_base));
+    }
+    match snapshot.get(AT_SYSINFO_EHDR) {
+        Some(0) | None => {}
+        Some(ehdr) => add(object_at(VDSO_NAME, ehdr as usize))
+    }
+
+    Ok(objects)
+}
+
+/// The names, load biases and dynamic section addresses in the dynamic linker's `link_map` list,
+/// found via the executable's `DT_DEBUG` entry. Empty if there's no dynamic linker, or it hasn't set
+/// up the list.
+unsafe fn link_map_entries(headers: ProgramHeaders, load_bias: usize)
+                           -> Vec<(&'static [u8], usize, usize)> {
+    let dynamic = match headers.dynamic() {
+        Some(p) => slice::from_raw_parts((p.p_vaddr as usize).wrapping_add(load_bias) as *const u8,
+                                         p.p_memsz as usize),
+        None => return Vec::new()
+    };
+    let r_debug = match iterate_dynamic(dynamic).find(|&(tag, _)| tag == DT_DEBUG) {
+        Some((_, addr)) if addr != 0 => &*(addr as *const RDebug),
+        _ => return Vec::new()
+    };
+    if r_debug.r_version < 1 {
+        return Vec::new();
+    }
+
+    let mut entries = Vec::new();
+    let mut entry = r_debug.r_map;
+    while !entry.is_null() && entries.len() < MAX_LINK_MAP_ENTRIES {
+        let link_map = &*entry;
+        let name = if link_map.l_name.is_null() { &b""[..] } else { c_str(link_map.l_name as usize) };
+        entries.push((name, link_map.l_addr, link_map.l_ld));
+        entry = link_map.l_next;
+    }
+    entries
+}
+
+/// The object a `link_map` entry describes, if its ELF header is at its load bias `l_addr`, as
+/// it is when its first segment is at address 0. The headers found there have to put the dynamic
+/// section at `l_ld`.
+unsafe fn object_at_load_bias(name: &'static [u8], l_addr: usize, l_ld: usize)
+                              -> Option<LoadedObject<'static>> {
+    // a load bias of 0 means the object is where it was linked to be, so its header isn't at 0
+    if l_addr == 0 {
+        return None;
+    }
+    object_at(name, l_addr)
+        .filter(|o| o.base == l_addr)
+        .filter(|o| {
+            o.headers.dynamic().map(|p| (p.p_vaddr as usize).wrapping_add(l_addr)) == Some(l_ld)
+        })
+}
+
+/// Where the ELF header of the object whose dynamic section is at `l_ld` is mapped: the start of the
+/// nearest mapping at or below `l_ld` of the start of the same file.
+fn ehdr_for_dynamic(mappings: &[MemoryMapping], l_ld: usize) -> Option<usize> {
+    let index = mappings.iter().position(|m| m.contains(l_ld as u64))?;
+    let path = &mappings[index].path;
+    if path.is_empty() {
+        return None;
+    }
+    mappings[..=index].iter().rev()
+        .find(|m| m.path == *path && m.offset == 0)
+        .filter(|m| m.perms.starts_with('r'))
+        .map(|m| m.start as usize)
+}
+
+/// The object whose ELF header is at `ehdr`, with its load bias computed from the `PT_LOAD` segment
+/// that maps the header.
+unsafe fn object_at(name: &'static [u8], ehdr: usize) -> Option<LoadedObject<'static>> {
+    let headers = ProgramHeaders::from_ehdr(ehdr).ok()?;
+    let vaddr = headers.iter().find(|p| p.p_type == PT_LOAD && p.p_offset == 0)?.p_vaddr as usize;
+    Some(LoadedObject {
+        name,
+        base: ehdr.checked_sub(vaddr)?,
+        headers,
+    })
+}
+
+unsafe fn c_str(addr: usize) -> &'static [u8] {
+    CStr::from_ptr(addr as *const c_char).to_bytes()
+}
+
+#[cfg(all(test, target_pointer_width="64"))]
+mod tests {
+    use std::ptr;
+
+    use super::{ehdr_for_dynamic, link_map_entries, loaded_objects_from_snapshot, LinkMap,
+                LinkMapError, RDebug, VDSO_NAME};
+    use super::super::{AuxvPair, AuxvType, AT_BASE, AT_ENTRY, AT_PHDR, AT_PHENT, AT_PHNUM,
+                       AT_SYSINFO_EHDR};
+    use super::super::elf::{ElfPhdr, DT_DEBUG, PT_DYNAMIC, PT_INTERP, PT_LOAD, PT_PHDR};
+    use super::super::phdr::ProgramHeaders;
+    use super::super::procfs::MemoryMapping;
+    use super::super::snapshot::AuxvSnapshot;
+
+    fn phdr(p_type: u32, p_vaddr: u64, p_memsz: u64) -> ElfPhdr {
+        ElfPhdr {
+            p_type,
+            p_flags: 0,
+            p_offset: p_vaddr,
+            p_vaddr,
+            p_paddr: p_vaddr,
+            p_filesz: p_memsz,
+            p_memsz,
+            p_align: 8,
+        }
+    }
+
+    /// A fake mapped object: an ELF header at the start, followed by its program headers.
+    fn object(headers: &[ElfPhdr]) -> Vec<u64> {
+        let mut image = vec![0u64; 512];
+        let bytes = image.as_mut_ptr() as *mut u8;
+        unsafe {
+            ptr::copy_nonoverlapping(b"\x7fELF\x02".as_ptr(), bytes, 5);
+            *bytes.add(5) = if cfg!(target_endian = "little") { 1 } else { 2 };
+            // e_phoff, then e_phentsize and e_phnum
+            *(bytes.add(32) as *mut u64) = 64;
+            *(bytes.add(54) as *mut u16) = std::mem::size_of::<ElfPhdr>() as u16;
+            *(bytes.add(56) as *mut u16) = headers.len() as u16;
+            ptr::copy_nonoverlapping(headers.as_ptr(), bytes.add(64) as *mut ElfPhdr, headers.len());
+        }
+        image
+    }
+
+    #[test]
+    fn test_loaded_objects_walks_link_map_and_dedups() {
+        let mut exe = object(&[
+            phdr(PT_PHDR, 0x40, 4 * 56),
+            phdr(PT_LOAD, 0, 0x1000),
+            phdr(PT_DYNAMIC, 0x800, 0x20),
+            phdr(PT_INTERP, 0x900, 11),
+        ]);
+        let lib = object(&[phdr(PT_LOAD, 0, 0x1000), phdr(PT_DYNAMIC, 0x800, 0x10)]);
+        let interpreter = object(&[phdr(PT_LOAD, 0, 0x1000), phdr(PT_DYNAMIC, 0x800, 0x10)]);
+        let vdso = object(&[phdr(PT_LOAD, 0, 0x1000)]);
+
+        let exe_base = exe.as_ptr() as usize;
+        let (lib_base, interpreter_base) = (lib.as_ptr() as usize, interpreter.as_ptr() as usize);
+
+        let interpreter_entry = LinkMap {
+            l_addr: interpreter_base,
+            l_name: b"/lib/ld.so\0".as_ptr() as *const _,
+            l_ld: interpreter_base + 0x800,
+            l_next: ptr::null(),
+            l_prev: ptr::null(),
+        };
+        let lib_entry = LinkMap {
+            l_addr: lib_base,
+            l_name: b"/lib/libfoo.so\0".as_ptr() as *const _,
+            l_ld: lib_base + 0x800,
+            l_next: &interpreter_entry,
+            l_prev: ptr::null(),
+        };
+        let exe_entry = LinkMap {
+            l_addr: exe_base,
+            l_name: b"\0".as_ptr() as *const _,
+            l_ld: 0,
+            l_next: &lib_entry,
+            l_prev: ptr::null(),
+        };
+        let r_debug = RDebug { r_version: 1, r_map: &exe_entry };
+        // the dynamic section at 0x800: DT_DEBUG, then DT_NULL
+        exe[0x800 / 8] = DT_DEBUG as u64;
+        exe[0x800 / 8 + 1] = &r_debug as *const RDebug as u64;
+        exe[0x900 / 8] = u64::from_ne_bytes(*b"/lib/ld.");
+        exe[0x900 / 8 + 1] = u64::from_ne_bytes(*b"so\0\0\0\0\0\0");
+
+        let snapshot = AuxvSnapshot::new(vec![
+            AuxvPair { key: AT_PHDR, value: (exe_base + 0x40) as AuxvType },
+            AuxvPair { key: AT_PHENT, value: 56 },
+            AuxvPair { key: AT_PHNUM, value: 4 },
+            AuxvPair { key: AT_ENTRY, value: (exe_base + 0x100) as AuxvType },
+            AuxvPair { key: AT_BASE, value: interpreter_base as AuxvType },
+            AuxvPair { key: AT_SYSINFO_EHDR, value: vdso.as_ptr() as AuxvType },
+        ]);
+
+        let headers = unsafe { ProgramHeaders::from_snapshot(&snapshot).unwrap() };
+        assert_eq!(vec![(&b""[..], exe_base, 0),
+                        (&b"/lib/libfoo.so"[..], lib_base, lib_base + 0x800),
+                        (&b"/lib/ld.so"[..], interpreter_base, interpreter_base + 0x800)],
+                   unsafe { link_map_entries(headers, exe_base) });
+
+        // found at their load biases, without /proc/self/maps
+        let objects = unsafe { loaded_objects_from_snapshot(&snapshot).unwrap() };
+        let summary: Vec<(&[u8], usize, usize)> = objects.iter()
+            .map(|o| (o.name, o.base, o.headers.as_slice().len()))
+            .collect();
+        assert_eq!(vec![(&b""[..], exe_base, 4),
+                        (&b"/lib/libfoo.so"[..], lib_base, 2),
+                        (&b"/lib/ld.so"[..], interpreter_base, 2),
+                        (VDSO_NAME, vdso.as_ptr() as usize, 1)],
+                   summary);
+    }
+
+    #[test]
+    fn test_loaded_objects_header_not_found() {
+        let mut exe = object(&[
+            phdr(PT_PHDR, 0x40, 3 * 56),
+            phdr(PT_LOAD, 0, 0x1000),
+            phdr(PT_DYNAMIC, 0x800, 0x20),
+        ]);
+        let lib = object(&[phdr(PT_LOAD, 0, 0x1000), phdr(PT_DYNAMIC, 0x800, 0x10)]);
+        let exe_base = exe.as_ptr() as usize;
+        let lib_base = lib.as_ptr() as usize;
+
+        // the dynamic section doesn't match the headers at the load bias, and the fake library
+        // isn't in a file mapping for /proc/self/maps to find
+        let lib_entry = LinkMap {
+            l_addr: lib_base,
+            l_name: b"/lib/libfoo.so\0".as_ptr() as *const _,
+            l_ld: lib_base + 0x900,
+            l_next: ptr::null(),
+            l_prev: ptr::null(),
+        };
+        let r_debug = RDebug { r_version: 1, r_map: &lib_entry };
+        exe[0x800 / 8] = DT_DEBUG as u64;
+        exe[0x800 / 8 + 1] = &r_debug as *const RDebug as u64;
+
+        let snapshot = AuxvSnapshot::new(vec![
+            AuxvPair { key: AT_PHDR, value: (exe_base + 0x40) as AuxvType },
+            AuxvPair { key: AT_PHENT, value: 56 },
+            AuxvPair { key: AT_PHNUM, value: 3 },
+            AuxvPair { key: AT_ENTRY, value: (exe_base + 0x100) as AuxvType },
+        ]);
+
+        assert_eq!(LinkMapError::HeaderNotFound,
+                   unsafe { loaded_objects_from_snapshot(&snapshot).unwrap_err() });
+    }
+
+    #[test]
+    fn test_loaded_objects_static_without_dynamic() {
+        let exe = object(&[phdr(PT_PHDR, 0x40, 2 * 56), phdr(PT_LOAD, 0, 0x1000)]);
+        let exe_base = exe.as_ptr() as usize;
+
+        let snapshot = AuxvSnapshot::new(vec![
+            AuxvPair { key: AT_PHDR, value: (exe_base + 0x40) as AuxvType },
+            AuxvPair { key: AT_PHENT, value: 56 },
+            AuxvPair { key: AT_PHNUM, value: 2 },
+            AuxvPair { key: AT_ENTRY, value: (exe_base + 0x100) as AuxvType },
+            AuxvPair { key: AT_BASE, value: 0 },
+        ]);
+
+        let objects = unsafe { loaded_objects_from_snapshot(&snapshot).unwrap() };
+        assert_eq!(1, objects.len());
+        assert_eq!(exe_base, objects[0].base);
+    }
+
+    #[test]
+    fn test_ehdr_for_dynamic() {
+        let mapping = |start: u64, perms: &str, offset: u64, path: &str| MemoryMapping {
+            start,
+            end: start + 0x1000,
+            perms: perms.to_owned(),
+            offset,
+            path: path.to_owned(),
+        };
+        let mappings = vec![
+            mapping(0x1000, "r--p", 0, "/lib/liba.so"),
+            mapping(0x2000, "r-xp", 0x1000, "/lib/liba.so"),
+            mapping(0x3000, "rw-p", 0x2000, "/lib/liba.so"),
+            mapping(0x4000, "r--p", 0, "/lib/libb.so"),
+            mapping(0x5000, "rw-p", 0x1000, "/lib/libb.so"),
+            mapping(0x6000, "rw-p", 0, ""),
+            mapping(0x8000, "rw-p", 0x1000, "/lib/libc.so"),
+        ];
+
+        assert_eq!(Some(0x1000), ehdr_for_dynamic(&mappings, 0x3800));
+        assert_eq!(Some(0x4000), ehdr_for_dynamic(&mappings, 0x5800));
+        // anonymous, no start of the file mapped, and not mapped at all
+        assert_eq!(None, ehdr_for_dynamic(&mappings, 0x6800));
+        assert_eq!(None, ehdr_for_dynamic(&mappings, 0x8800));
+        assert_eq!(None, ehdr_for_dynamic(&mappings, 0x7800));
+    }
+}
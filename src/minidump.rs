@@ -0,0 +1,255 @@
+//! Read the aux vector captured in a Breakpad or Crashpad minidump.
+//!
+//! On Linux, Breakpad and Crashpad copy the crashed process's `/proc/<pid>/auxv` into a
+//! `MD_LINUX_AUXV` stream in the minidump. `read_minidump_auxv` finds that stream and parses it
+//! into an `AuxvSnapshot`, so you can look at `AT_HWCAP`, `AT_SYSINFO_EHDR` and friends without
+//! a core dump.
+//!
+//! The crashed process may not have the same word size as the one reading the dump (a 32-bit ARM
+//! process symbolicated on an x64 server, say), so the CPU architecture in the dump's system info
+//! stream is used to pick the auxv entry width. If there is no system info stream, or the
+//! architecture is one we don't recognize, the width of `AuxvType` is used. Minidumps are
+//! little-endian, and so is the auxv data in them.
+
+extern crate byteorder;
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use self::byteorder::{LittleEndian, ReadBytesExt};
+
+use super::ElfClass;
+use super::bytes::iterate_bytes_auxv_class;
+use super::snapshot::AuxvSnapshot;
+
+/// The stream type of the raw auxv copied from `/proc/<pid>/auxv`.
+pub const MD_LINUX_AUXV: u32 = 0x4767_0008;
+/// The stream type of `MINIDUMP_SYSTEM_INFO`.
+pub const MD_SYSTEM_INFO_STREAM: u32 = 7;
+
+// "MDMP" as a little-endian u32
+const MD_HEADER_SIGNATURE: u32 = 0x504d_444d;
+// the low 16 bits of the header version
+const MD_HEADER_VERSION: u32 = 0xa793;
+
+/// Errors from reading auxv out of a minidump.
+#[derive(Debug, PartialEq)]
+pub enum MinidumpAuxvError {
+    /// an io error was encountered
+    IoError,
+    /// the file is not a minidump, or its structure is damaged
+    InvalidFormat,
+    /// the minidump does not contain an `MD_LINUX_AUXV` stream
+    NoAuxvStream
+}
+
+/// Read the `MD_LINUX_AUXV` stream from the minidump file at `path`.
+pub fn read_minidump_auxv(path: &Path) -> Result<AuxvSnapshot, MinidumpAuxvError> {
+    let file = File::open(path).map_err(|_| MinidumpAuxvError::IoError)?;
+    read_auxv_stream(&mut BufReader::new(file))
+}
+
+/// Read the `MD_LINUX_AUXV` stream from a minidump.
+///
+/// Only the header, the stream directory, and the streams of interest are read, so it's fine to
+/// hand this a reader over a very large dump.
+pub fn read_auxv_stream<R: Read + Seek>(input: &mut R) -> Result<AuxvSnapshot, MinidumpAuxvError> {
+    let streams = read_directory(input)?;
+
+    let auxv_location = streams.iter()
+        .find(|s| s.stream_type == MD_LINUX_AUXV)
+        .ok_or(MinidumpAuxvError::NoAuxvStream)?;
+
+    let class = match streams.iter().find(|s| s.stream_type == MD_SYSTEM_INFO_STREAM) {
+        Some(s) => {
+            seek(input, s.rva)?;
+            let arch = input.read_u16::<LittleEndian>().map_err(map_read_err)?;
+            elf_class_for_cpu(arch).unwrap_or_else(ElfClass::native)
+        }
+        None => ElfClass::native()
+    };
+
+    // the size is whatever the dump claims, so make sure the data is really there before
+    // allocating room for it
+    let len = input.seek(SeekFrom::End(0)).map_err(|_| MinidumpAuxvError::IoError)?;
+    if u64::from(auxv_location.rva) + u64::from(auxv_location.data_size) > len {
+        return Err(MinidumpAuxvError::InvalidFormat);
+    }
+
+    let mut data = vec![0; auxv_location.data_size as usize];
+    seek(input, auxv_location.rva)?;
+    input.read_exact(&mut data).map_err(map_read_err)?;
+
+    iterate_bytes_auxv_class::<LittleEndian>(&data, class)
+        .map(|r| r.map_err(|_| MinidumpAuxvError::InvalidFormat))
+        .collect::<Result<Vec<_>, _>>()
+        .map(AuxvSnapshot::new)
+}
+
+/// An entry in the minidump stream directory.
+struct StreamLocation {
+    stream_type: u32,
+    data_size: u32,
+    rva: u32
+}
+
+fn read_directory<R: Read + Seek>(input: &mut R)
+                                  -> Result<Vec<StreamLocation>, MinidumpAuxvError> {
+    seek(input, 0)?;
+    let signature = input.read_u32::<LittleEndian>().map_err(map_read_err)?;
+    let version = input.read_u32::<LittleEndian>().map_err(map_read_err)?;
+    if signature != MD_HEADER_SIGNATURE || version & 0xffff != MD_HEADER_VERSION {
+        return Err(MinidumpAuxvError::InvalidFormat);
+    }
+    let stream_count = input.read_u32::<LittleEndian>().map_err(map_read_err)?;
+    let directory_rva = input.read_u32::<LittleEndian>().map_err(map_read_err)?;
+
+    seek(input, directory_rva)?;
+    let mut streams = Vec::new();
+    for _ in 0..stream_count {
+        let stream_type = input.read_u32::<LittleEndian>().map_err(map_read_err)?;
+        let data_size = input.read_u32::<LittleEndian>().map_err(map_read_err)?;
+        let rva = input.read_u32::<LittleEndian>().map_err(map_read_err)?;
+        streams.push(StreamLocation { stream_type, data_size, rva });
+    }
+
+    Ok(streams)
+}
+
+/// Map a `MINIDUMP_SYSTEM_INFO` processor architecture to the ELF class of processes on it.
+fn elf_class_for_cpu(arch: u16) -> Option<ElfClass> {
+    match arch {
+        // x86, MIPS, PPC, ARM, SPARC, RISCV
+        0 | 1 | 3 | 5 | 0x8001 | 0x8005 => Some(ElfClass::Elf32),
+        // IA64, AMD64, ARM64, PPC64, old Breakpad ARM64, MIPS64, RISCV64
+        6 | 9 | 12 | 0x8002 | 0x8003 | 0x8004 | 0x8006 => Some(ElfClass::Elf64),
+        _ => None
+    }
+}
+
+fn seek<R: Seek>(input: &mut R, rva: u32) -> Result<(), MinidumpAuxvError> {
+    input.seek(SeekFrom::Start(u64::from(rva)))
+        .map(|_| ())
+        .map_err(|_| MinidumpAuxvError::IoError)
+}
+
+/// Running out of data partway through a structure means the dump is damaged.
+fn map_read_err(e: std::io::Error) -> MinidumpAuxvError {
+    match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => MinidumpAuxvError::InvalidFormat,
+        _ => MinidumpAuxvError::IoError
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::byteorder::{LittleEndian, WriteBytesExt};
+    use super::{read_auxv_stream, MinidumpAuxvError, MD_LINUX_AUXV, MD_SYSTEM_INFO_STREAM};
+    use super::super::AuxvPair;
+    use super::super::test_support::read_fixture;
+
+    /// Build a minidump with the given streams, laid out after the header and directory.
+    fn build_minidump(streams: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let header_size = 32;
+        let directory_size = 12 * streams.len();
+
+        out.write_u32::<LittleEndian>(0x504d_444d).unwrap();
+        out.write_u32::<LittleEndian>(0xa793).unwrap();
+        out.write_u32::<LittleEndian>(streams.len() as u32).unwrap();
+        out.write_u32::<LittleEndian>(header_size).unwrap();
+        // checksum, timestamp, flags
+        out.write_u32::<LittleEndian>(0).unwrap();
+        out.write_u32::<LittleEndian>(0).unwrap();
+        out.write_u64::<LittleEndian>(0).unwrap();
+
+        let mut rva = header_size as usize + directory_size;
+        for (stream_type, data) in streams {
+            out.write_u32::<LittleEndian>(*stream_type).unwrap();
+            out.write_u32::<LittleEndian>(data.len() as u32).unwrap();
+            out.write_u32::<LittleEndian>(rva as u32).unwrap();
+            rva += data.len();
+        }
+        for (_, data) in streams {
+            out.extend_from_slice(data);
+        }
+
+        out
+    }
+
+    fn system_info(arch: u16) -> Vec<u8> {
+        let mut info = Vec::new();
+        info.write_u16::<LittleEndian>(arch).unwrap();
+        // the rest of MINIDUMP_SYSTEM_INFO is irrelevant here
+        info.extend_from_slice(&[0; 54]);
+        info
+    }
+
+    #[test]
+    fn test_read_auxv_stream_x86_system_info() {
+        let dump = build_minidump(&[
+            (MD_SYSTEM_INFO_STREAM, system_info(0)),
+            (MD_LINUX_AUXV, read_fixture("macos-virtualbox-linux-x86-4850HQ.auxv"))
+        ]);
+
+        let snapshot = read_auxv_stream(&mut Cursor::new(dump)).unwrap();
+        assert_eq!(19, snapshot.len());
+        // x86 AT_SYSINFO_EHDR
+        assert_eq!(Some(3078057984), snapshot.get(33));
+        // AT_PLATFORM
+        assert_eq!(&AuxvPair { key: 15, value: 3219671675 }, snapshot.pairs().last().unwrap());
+    }
+
+    #[test]
+    fn test_read_auxv_stream_amd64_system_info() {
+        let dump = build_minidump(&[
+            (MD_LINUX_AUXV, read_fixture("linux-x64-i7-6850k.auxv")),
+            (MD_SYSTEM_INFO_STREAM, system_info(9))
+        ]);
+
+        let snapshot = read_auxv_stream(&mut Cursor::new(dump)).unwrap();
+        assert_eq!(18, snapshot.len());
+        // AT_HWCAP
+        assert_eq!(Some(3219913727), snapshot.get(16));
+    }
+
+    #[test]
+    fn test_read_auxv_stream_truncated_auxv_invalidformat() {
+        let dump = build_minidump(&[
+            (MD_SYSTEM_INFO_STREAM, system_info(9)),
+            (MD_LINUX_AUXV, read_fixture("linux-x64-i7-6850k-mangled-truncated-entry.auxv"))
+        ]);
+
+        assert_eq!(MinidumpAuxvError::InvalidFormat,
+                   read_auxv_stream(&mut Cursor::new(dump)).unwrap_err());
+    }
+
+    #[test]
+    fn test_read_auxv_stream_size_past_end_invalidformat() {
+        let mut dump = build_minidump(&[(MD_LINUX_AUXV, read_fixture("linux-x64-i7-6850k.auxv"))]);
+        // the auxv stream's data_size, in the directory right after the 32 byte header
+        (&mut dump[36..40]).write_u32::<LittleEndian>(0xffff_fff0).unwrap();
+
+        assert_eq!(MinidumpAuxvError::InvalidFormat,
+                   read_auxv_stream(&mut Cursor::new(dump)).unwrap_err());
+    }
+
+    #[test]
+    fn test_read_auxv_stream_no_auxv_stream() {
+        let dump = build_minidump(&[(MD_SYSTEM_INFO_STREAM, system_info(9))]);
+
+        assert_eq!(MinidumpAuxvError::NoAuxvStream,
+                   read_auxv_stream(&mut Cursor::new(dump)).unwrap_err());
+    }
+
+    #[test]
+    fn test_read_auxv_stream_not_a_minidump() {
+        let auxv = read_fixture("linux-x64-i7-6850k.auxv");
+
+        assert_eq!(MinidumpAuxvError::InvalidFormat,
+                   read_auxv_stream(&mut Cursor::new(auxv)).unwrap_err());
+    }
+}
@@ -0,0 +1,145 @@
+//! The main executable's program headers, found via `AT_PHDR`, `AT_PHENT` and `AT_PHNUM`.
+//!
+//! The kernel maps the executable's program headers into memory and tells the process where they
+//! are in auxv, so a process can see its own segments (dynamic section, TLS template, notes, ...)
+//! without opening `/proc/self/exe`, which may not even be the same file any more.
+//!
+//! `ProgramHeaders::current` finds them for the current process. If you already have the auxv
+//! values, `ProgramHeaders::from_raw` and `ProgramHeaders::from_snapshot` take it from there;
+//! they're `unsafe` because the addresses have to be valid in the current process. Either way,
+//! `AT_PHENT` is checked against the size of `ElfPhdr` before anything is dereferenced.
+
+use std::slice;
+
+use super::{AuxvType, AT_PHDR, AT_PHENT, AT_PHNUM};
+use super::elf::{ElfImage, ElfPhdr, EHDR_SIZE, PT_DYNAMIC, PT_GNU_RELRO, PT_GNU_STACK, PT_INTERP, PT_NOTE, PT_TLS};
+use super::snapshot::AuxvSnapshot;
+
+/// Errors from locating program headers.
+#[derive(Debug, PartialEq)]
+pub enum PhdrError {
+    /// `AT_PHDR`, `AT_PHENT` or `AT_PHNUM` is missing
+    NotAvailable,
+    /// `AT_PHENT` doesn't match the size of `ElfPhdr`, or `AT_PHDR` is not suitably aligned
+    InvalidFormat
+}
+
+/// A process's program headers.
+#[derive(Debug, Clone, Copy)]
+pub struct ProgramHeaders<'a> {
+    headers: &'a [ElfPhdr],
+}
+
+impl<'a> ProgramHeaders<'a> {
+    /// Wrap program headers that are already available as a slice.
+    pub fn new(headers: &'a [ElfPhdr]) -> ProgramHeaders<'a> {
+        ProgramHeaders { headers }
+    }
+
+    /// Use the program headers at `phdr`, as described by `AT_PHDR`, `AT_PHENT` and `AT_PHNUM`.
+    ///
+    /// # Safety
+    ///
+    /// `phdr` must point to `phnum` program headers in the current process's memory that live for
+    /// `'a`, as the current process's `AT_PHDR` does.
+    pub unsafe fn from_raw(phdr: AuxvType, phent: AuxvType, phnum: AuxvType)
+                           -> Result<ProgramHeaders<'a>, PhdrError> {
+        let ptr = phdr as usize as *const ElfPhdr;
+        if phdr == 0 || phent as usize != std::mem::size_of::<ElfPhdr>()
+            || !(ptr as usize).is_multiple_of(std::mem::align_of::<ElfPhdr>()) {
+            return Err(PhdrError::InvalidFormat);
+        }
+
+        Ok(ProgramHeaders::new(slice::from_raw_parts(ptr, phnum as usize)))
+    }
+
+    /// Use the program headers described by a snapshot's `AT_PHDR`, `AT_PHENT` and `AT_PHNUM`.
+    ///
+    /// # Safety
+    ///
+    /// The snapshot must be of the current process's auxv; see `from_raw`.
+    pub unsafe fn from_snapshot(snapshot: &AuxvSnapshot) -> Result<ProgramHeaders<'a>, PhdrError> {
+        match (snapshot.get(AT_PHDR), snapshot.get(AT_PHENT), snapshot.get(AT_PHNUM)) {
+            (Some(phdr), Some(phent), Some(phnum)) => ProgramHeaders::from_raw(phdr, phent, phnum),
+            _ => Err(PhdrError::NotAvailable)
+        }
+    }
+
+    /// Use the program headers of the ELF image whose header is mapped at `ehdr`, e.g. a shared
+    /// library or the vDSO.
+    ///
+    /// # Safety
+    ///
+    /// `ehdr` must point to an ELF header in the current process's memory, followed by its program
+    /// headers, that live for `'a`.
+    pub unsafe fn from_ehdr(ehdr: usize) -> Result<ProgramHeaders<'a>, PhdrError> {
+        if ehdr == 0 {
+            return Err(PhdrError::NotAvailable);
+        }
+        let image = ElfImage::parse(slice::from_raw_parts(ehdr as *const u8, EHDR_SIZE))
+            .map_err(|_| PhdrError::InvalidFormat)?;
+        let phdr = ehdr.checked_add(image.program_header_offset()).ok_or(PhdrError::InvalidFormat)?;
+        ProgramHeaders::from_raw(phdr as AuxvType, image.program_header_size() as AuxvType,
+                                 image.program_header_count() as AuxvType)
+    }
+
+    /// The program headers of the current process's main executable.
+    pub fn current() -> Result<ProgramHeaders<'static>, PhdrError> {
+        let value = |key| super::current_auxv_value(key).ok_or(PhdrError::NotAvailable);
+        let (phdr, phent, phnum) = (value(AT_PHDR)?, value(AT_PHENT)?, value(AT_PHNUM)?);
+        unsafe { ProgramHeaders::from_raw(phdr, phent, phnum) }
+    }
+
+    /// The headers as a slice.
+    pub fn as_slice(&self) -> &'a [ElfPhdr] {
+        self.headers
+    }
+
+    /// Iterate over the headers.
+    pub fn iter(&self) -> slice::Iter<'a, ElfPhdr> {
+        self.headers.iter()
+    }
+
+    /// The first header with type `p_type`.
+    pub fn find(&self, p_type: u32) -> Option<&'a ElfPhdr> {
+        self.headers.iter().find(|p| p.p_type == p_type)
+    }
+
+    /// The `PT_DYNAMIC` header, which is present in dynamically linked executables.
+    pub fn dynamic(&self) -> Option<&'a ElfPhdr> {
+        self.find(PT_DYNAMIC)
+    }
+
+    /// The `PT_TLS` header, which describes the thread-local storage template.
+    pub fn tls(&self) -> Option<&'a ElfPhdr> {
+        self.find(PT_TLS)
+    }
+
+    /// The `PT_GNU_STACK` header, whose flags say whether the stack is executable.
+    pub fn gnu_stack(&self) -> Option<&'a ElfPhdr> {
+        self.find(PT_GNU_STACK)
+    }
+
+    /// The `PT_GNU_RELRO` header, which describes memory that's read-only after relocation.
+    pub fn gnu_relro(&self) -> Option<&'a ElfPhdr> {
+        self.find(PT_GNU_RELRO)
+    }
+
+    /// The `PT_INTERP` header, which names the program interpreter.
+    pub fn interp(&self) -> Option<&'a ElfPhdr> {
+        self.find(PT_INTERP)
+    }
+
+    /// The `PT_NOTE` headers. There's often more than one.
+    pub fn notes(&self) -> impl Iterator<Item = &'a ElfPhdr> {
+        self.headers.iter().filter(|p| p.p_type == PT_NOTE)
+    }
+}
+
+impl<'a> IntoIterator for ProgramHeaders<'a> {
+    type Item = &'a ElfPhdr;
+    type IntoIter = slice::Iter<'a, ElfPhdr>;
+    fn into_iter(self) -> slice::Iter<'a, ElfPhdr> {
+        self.headers.iter()
+    }
+}
@@ -8,24 +8,61 @@
 //! good choice. You provide a slice of keys to look for, and it builds a map of key
 //! to value for the keys you specify.
 //!
+//! `search_procfs_auxv_array` does the same for a fixed number of keys without allocating, for
+//! startup code and other places where that matters.
+//!
 //! If, on the other hand, you want to inspect everything in the aux vector, `iterate_procfs_auxv`
 //! is what you want. It will let you iterate over every key/value pair in the aux vector. A minor
 //! wrinkle is that there are two layers of `Result`: one for around the initial `Iterator`, and
 //! another around each key/value pair. That's just the way I/O is...
+//!
+//! Both of those read the file a pair at a time, so if the process execs part way through, they
+//! can see the end of one aux vector after the start of another. The kernel produces the whole
+//! file in one `read`, so `ProcfsAuxvReader` reads it all at once into a buffer and parses it
+//! afterwards. The buffer is kept between reads, so a scanner going through every process in
+//! `/proc` can use one reader for all of them instead of allocating for each.
+//!
+//! A pid can be reused as soon as its process exits, so a reader working through other
+//! processes can end up reading a different process than the one it meant to. On Linux 5.3+,
+//! `ProcfsAuxvReader::read_pidfd` and `read_pid_via_pidfd` use a pidfd to make sure the auxv they
+//! return came from the process the pidfd refers to.
+//!
+//! Threads normally share their process's aux vector, but `/proc/<pid>/task/<tid>/auxv` shows
+//! each thread's own view, which is what matters after an `execve` from a multithreaded process
+//! or in unusual `ptrace` states. `Target::Task` reads those, and `list_procfs_tasks` and
+//! `Procfs::read_task_auxv` list a process's threads.
+//!
+//! All of these read from `/proc`. To read from a procfs mounted somewhere else, or a fake one, use
+//! the same operations on a `Procfs` created with `Procfs::with_root`.
+//!
+//! Many auxv values are addresses, and to do anything with them you often need to know what's
+//! mapped there. `read_procfs_maps` reads `/proc/<pid>/maps` for that purpose.
+//!
+//! `read_procfs_stack_layout` reads where the kernel put the initial stack, arguments and
+//! environment from `/proc/<pid>/stat`, which is what the `stack` module uses to tell whether
+//! `environ` has been moved.
 
 
 extern crate byteorder;
 
 use std::collections::HashMap;
-use std::io::{BufReader, Read};
+use std::fmt::Write;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::fs::File;
-use std::path::Path;
+#[cfg(target_os="linux")]
+use std::ffi::c_long;
+#[cfg(target_os="linux")]
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::marker::PhantomData;
 use std;
 
 use self::byteorder::{ByteOrder, ReadBytesExt, NativeEndian};
 
-use super::{AuxvPair, AuxvType};
+use super::{try_search_auxv, AuxvPair, AuxvType, ElfClass};
+use super::bytes::{iterate_bytes_auxv, iterate_bytes_auxv_class};
+use super::snapshot::AuxvSnapshot;
 
 /// Read from the procfs auxv file and look for the specified keys.
 ///
@@ -34,22 +71,472 @@ use super::{AuxvPair, AuxvType};
 /// requested that also had values in the aux vector
 pub fn search_procfs_auxv(keys: &[AuxvType])
                           -> Result<HashMap<AuxvType, AuxvType>, ProcfsAuxvError> {
-    let mut result = HashMap::<AuxvType, AuxvType>::new();
+    Procfs::new().search_auxv(Target::Current, keys)
+}
+
+/// Which process's files in procfs to read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// the calling process, via `self`
+    Current,
+    /// the process with this pid, as seen by the procfs mount
+    Pid(u32),
+    /// one thread of a process, via `<pid>/task/<tid>`
+    Task {
+        pid: u32,
+        tid: u32,
+    },
+}
+
+/// A procfs mount to read from.
+///
+/// The free functions in this module all use `/proc`. If procfs is somewhere else, like the host's
+/// procfs mounted into a container at `/host/proc`, or a fake tree in a test, use a `Procfs` with
+/// that root instead. Pids are as seen by whichever pid namespace the mount belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Procfs {
+    root: PathBuf,
+}
 
-    for r in iterate_path::<NativeEndian>(&Path::new("/proc/self/auxv"))? {
+impl Default for Procfs {
+    fn default() -> Procfs {
+        Procfs::new()
+    }
+}
+
+impl Procfs {
+    /// Use the procfs mounted at `/proc`.
+    pub fn new() -> Procfs {
+        Procfs::with_root("/proc")
+    }
 
-        let pair = match r {
-            Ok(p) => p,
-            Err(e) => return Err(e)
+    /// Use the procfs (or something laid out like it) at `root`.
+    pub fn with_root<P: Into<PathBuf>>(root: P) -> Procfs {
+        Procfs { root: root.into() }
+    }
+
+    /// The directory this reads from.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The path of `file` (e.g. `auxv`) for `target`.
+    pub fn path(&self, target: Target, file: &str) -> PathBuf {
+        let mut path = OsString::new();
+        self.write_path(&mut path, target, file);
+        PathBuf::from(path)
+    }
+
+    /// Replace the contents of `path` with the path of `file` for `target`, reusing its allocation.
+    fn write_path(&self, path: &mut OsString, target: Target, file: &str) {
+        path.clear();
+        path.push(self.root.as_os_str());
+        let _ = match target {
+            Target::Current => write!(path, "/self/{}", file),
+            Target::Pid(pid) => write!(path, "/{}/{}", pid, file),
+            Target::Task { pid, tid } => write!(path, "/{}/task/{}/{}", pid, tid, file),
         };
+    }
+
+    /// Look for the specified keys in `target`'s aux vector, like `search_procfs_auxv`.
+    pub fn search_auxv(&self, target: Target, keys: &[AuxvType])
+                       -> Result<HashMap<AuxvType, AuxvType>, ProcfsAuxvError> {
+        let mut result = HashMap::<AuxvType, AuxvType>::new();
+        let mut reader = self.auxv_reader();
+
+        for pair in reader.read_target(target)
+            .and_then(|bytes| parse_whole_file(bytes, ElfClass::native()))?
+            .pairs() {
 
-        if keys.contains(&pair.key) {
-            let _ = result.insert(pair.key, pair.value);
+            if keys.contains(&pair.key) {
+                let _ = result.insert(pair.key, pair.value);
+            }
         }
+
+        Ok(result)
+    }
+
+    /// Look for the specified keys in `target`'s aux vector, like `search_procfs_auxv_array`.
+    ///
+    /// Building the path allocates, so for the current process in `/proc`, where that matters,
+    /// use `search_procfs_auxv_array` instead.
+    pub fn search_auxv_array<const N: usize>(&self, target: Target, keys: [AuxvType; N])
+                                             -> Result<[Option<AuxvType>; N], ProcfsAuxvError> {
+        let file = File::open(self.path(target, "auxv")).map_err(map_io_err)?;
+        search_file_array(file, keys)
+    }
+
+    /// Iterate over `target`'s aux vector, like `iterate_procfs_auxv`.
+    pub fn iterate_auxv(&self, target: Target)
+                        -> Result<ProcfsAuxvIter<NativeEndian, File>, ProcfsAuxvError> {
+        iterate_path::<NativeEndian>(&self.path(target, "auxv"))
+    }
+
+    /// Read `target`'s memory mappings, like `read_procfs_maps`.
+    pub fn read_maps(&self, target: Target) -> Result<Vec<MemoryMapping>, ProcfsAuxvError> {
+        let input = File::open(self.path(target, "maps"))
+            .map_err(map_io_err)?;
+
+        let mut mappings = Vec::new();
+        for line in BufReader::new(input).lines() {
+            let line = line.map_err(map_io_err)?;
+            mappings.push(parse_maps_line(&line).ok_or(ProcfsAuxvError::InvalidFormat)?);
+        }
+
+        Ok(mappings)
+    }
+
+    /// Read `target`'s initial stack layout, like `read_procfs_stack_layout`.
+    pub fn read_stack_layout(&self, target: Target) -> Result<StackLayout, ProcfsAuxvError> {
+        let mut stat = String::new();
+        File::open(self.path(target, "stat"))
+            .and_then(|mut f| f.read_to_string(&mut stat))
+            .map_err(map_io_err)?;
+
+        parse_stat_stack_layout(&stat).ok_or(ProcfsAuxvError::InvalidFormat)
+    }
+
+    /// The pids of every process, from the numbered directories in the root, in ascending order.
+    pub fn list_pids(&self) -> Result<Vec<u32>, ProcfsAuxvError> {
+        list_numbered_dirs(&self.root)
     }
 
-    return Ok(result);
+    /// The thread ids of process `pid`, from `<pid>/task`, in ascending order.
+    pub fn list_tasks(&self, pid: u32) -> Result<Vec<u32>, ProcfsAuxvError> {
+        list_numbered_dirs(&self.path(Target::Pid(pid), "task"))
+    }
+
+    /// True if `target` is a kernel thread, which has no aux vector or executable, from the flags
+    /// in its `stat`.
+    pub fn is_kernel_thread(&self, target: Target) -> Result<bool, ProcfsAuxvError> {
+        let mut stat = String::new();
+        File::open(self.path(target, "stat"))
+            .and_then(|mut f| f.read_to_string(&mut stat))
+            .map_err(map_io_err)?;
+
+        stat_field(&stat, 9)
+            .map(|flags| flags & PF_KTHREAD != 0)
+            .ok_or(ProcfsAuxvError::InvalidFormat)
+    }
 
+    /// The aux vector of every thread of process `pid`, as seen by each thread.
+    ///
+    /// Threads can exit while this is running, so each one has its own `Result`.
+    pub fn read_task_auxv(&self, pid: u32, class: ElfClass) -> Result<Vec<TaskAuxv>, ProcfsAuxvError> {
+        let mut reader = self.auxv_reader();
+        Ok(self.list_tasks(pid)?
+            .into_iter()
+            .map(|tid| TaskAuxv {
+                tid,
+                auxv: reader.read_target(Target::Task { pid, tid })
+                    .and_then(|bytes| parse_whole_file(bytes, class))
+            })
+            .collect())
+    }
+
+    /// A `ProcfsAuxvReader` that reads from this procfs.
+    pub fn auxv_reader(&self) -> ProcfsAuxvReader {
+        ProcfsAuxvReader {
+            procfs: self.clone(),
+            buf: Vec::new(),
+            path: OsString::new(),
+        }
+    }
+}
+
+/// The names of the entries in `dir` that are numbers, in ascending order.
+fn list_numbered_dirs(dir: &Path) -> Result<Vec<u32>, ProcfsAuxvError> {
+    let mut ids = Vec::new();
+    for entry in dir.read_dir().map_err(map_io_err)? {
+        let entry = entry.map_err(map_io_err)?;
+        // anything else isn't a process or task
+        if let Some(id) = entry.file_name().to_str().and_then(|n| n.parse().ok()) {
+            ids.push(id);
+        }
+    }
+
+    ids.sort_unstable();
+    Ok(ids)
+}
+
+/// The `flags` bit in `stat` for kernel threads.
+const PF_KTHREAD: u64 = 0x0020_0000;
+
+/// One thread's aux vector, from `Procfs::read_task_auxv`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskAuxv {
+    /// The thread id
+    pub tid: u32,
+    /// The aux vector, or why it couldn't be read
+    pub auxv: Result<AuxvSnapshot, ProcfsAuxvError>,
+}
+
+/// Reads whole procfs auxv files at once, into a buffer that's reused from one read to the next.
+#[derive(Debug, Default)]
+pub struct ProcfsAuxvReader {
+    procfs: Procfs,
+    buf: Vec<u8>,
+    path: OsString,
+}
+
+impl ProcfsAuxvReader {
+    /// Create a reader for `/proc`. The buffer is allocated on the first read, and grows as
+    /// needed. Use `Procfs::auxv_reader` to read from somewhere else.
+    pub fn new() -> ProcfsAuxvReader {
+        ProcfsAuxvReader::default()
+    }
+
+    /// Read `<pid>/auxv` under the procfs root, returning its raw contents.
+    pub fn read_pid(&mut self, pid: u32) -> Result<&[u8], ProcfsAuxvError> {
+        self.read_target(Target::Pid(pid))
+    }
+
+    /// Read `target`'s auxv, returning its raw contents.
+    pub fn read_target(&mut self, target: Target) -> Result<&[u8], ProcfsAuxvError> {
+        // build the path in a buffer that's kept around too
+        let mut path = std::mem::take(&mut self.path);
+        self.procfs.write_path(&mut path, target, "auxv");
+
+        let result = self.read_path(Path::new(&path)).map(|bytes| bytes.len());
+        self.path = path;
+        result.map(move |len| &self.buf[..len])
+    }
+
+    /// Read an auxv file, returning its raw contents.
+    ///
+    /// The file is read with a single `read` if the buffer is big enough, and from the start again
+    /// with a bigger buffer if it isn't, so what's returned was all produced at once. A file that
+    /// doesn't fit in `MAX_READ_BUFFER_BYTES` is too big to be an aux vector, and is
+    /// `InvalidFormat`.
+    pub fn read_path(&mut self, path: &Path) -> Result<&[u8], ProcfsAuxvError> {
+        let mut file = File::open(path).map_err(map_io_err)?;
+
+        if self.buf.is_empty() {
+            self.buf.resize(INITIAL_READ_BUFFER_BYTES, 0);
+        }
+
+        loop {
+            let len = loop {
+                match file.read(&mut self.buf) {
+                    Ok(n) => break n,
+                    Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(map_io_err(e))
+                }
+            };
+
+            if len < self.buf.len() {
+                return Ok(&self.buf[..len]);
+            }
+
+            // might not have been all of it
+            if self.buf.len() >= MAX_READ_BUFFER_BYTES {
+                return Err(ProcfsAuxvError::InvalidFormat);
+            }
+            let bigger = self.buf.len() * 2;
+            self.buf.resize(bigger, 0);
+            file.seek(SeekFrom::Start(0)).map_err(map_io_err)?;
+        }
+    }
+
+    /// Read and parse `/proc/<pid>/auxv` for a process of the given class.
+    ///
+    /// Processes with no aux vector, like kernel threads and processes that haven't finished
+    /// `exec`ing, have an empty file, which is an empty snapshot. Anything else that isn't a
+    /// complete aux vector is `InvalidFormat`.
+    pub fn snapshot_pid(&mut self, pid: u32, class: ElfClass) -> Result<AuxvSnapshot, ProcfsAuxvError> {
+        self.read_pid(pid).and_then(|bytes| parse_whole_file(bytes, class))
+    }
+}
+
+#[cfg(target_os="linux")]
+impl ProcfsAuxvReader {
+    /// Read `/proc/<pid>/auxv` for the process `pidfd` refers to.
+    ///
+    /// Reading by pid alone can't tell whether the pid was reused by another process in between
+    /// deciding to read it and opening the file. A pidfd always refers to the same process, so
+    /// this reads the file and then checks, via the pidfd, that the process is still alive: if it
+    /// is, its pid can't have been reused, so the file belonged to it. If it isn't, this returns
+    /// `ProcessExited` rather than something that might have come from another process.
+    pub fn read_pidfd(&mut self, pidfd: BorrowedFd<'_>) -> Result<&[u8], ProcfsAuxvError> {
+        let pid = pidfd_pid(&self.procfs, pidfd)?;
+        let len = self.read_pid(pid)?.len();
+
+        if !pidfd_is_alive(pidfd)? {
+            return Err(ProcfsAuxvError::ProcessExited);
+        }
+
+        Ok(&self.buf[..len])
+    }
+
+    /// Open a pidfd for `pid`, and read its auxv with `read_pidfd`.
+    ///
+    /// This is for when all you have is a pid, e.g. from listing `/proc`: if the pid is reused
+    /// after this opens the pidfd, the read fails rather than reading the new process.
+    pub fn read_pid_via_pidfd(&mut self, pid: u32) -> Result<&[u8], ProcfsAuxvError> {
+        let pidfd = open_pidfd(pid)?;
+        self.read_pidfd(pidfd.as_fd())
+    }
+
+    /// Read and parse auxv for the process `pidfd` refers to, as with `read_pidfd`.
+    pub fn snapshot_pidfd(&mut self, pidfd: BorrowedFd<'_>, class: ElfClass)
+                          -> Result<AuxvSnapshot, ProcfsAuxvError> {
+        self.read_pidfd(pidfd).and_then(|bytes| parse_whole_file(bytes, class))
+    }
+}
+
+/// Open a pidfd for `pid` with `pidfd_open(2)`, which needs Linux 5.3.
+#[cfg(target_os="linux")]
+pub fn open_pidfd(pid: u32) -> Result<OwnedFd, ProcfsAuxvError> {
+    let fd = unsafe { syscall(SYS_PIDFD_OPEN, pid as c_long, 0 as c_long) };
+    if fd < 0 {
+        return Err(match std::io::Error::last_os_error().raw_os_error() {
+            Some(ESRCH) => ProcfsAuxvError::ProcessExited,
+            _ => ProcfsAuxvError::IoError
+        });
+    }
+
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+}
+
+/// True unless the kernel is too old to have `pidfd_open(2)`.
+#[cfg(target_os="linux")]
+pub(crate) fn pidfd_supported() -> bool {
+    let fd = unsafe { syscall(SYS_PIDFD_OPEN, std::process::id() as c_long, 0 as c_long) };
+    if fd < 0 {
+        return std::io::Error::last_os_error().raw_os_error() != Some(ENOSYS);
+    }
+
+    drop(unsafe { OwnedFd::from_raw_fd(fd as RawFd) });
+    true
+}
+
+/// The pid `pidfd` refers to, from the `Pid:` line in its fdinfo, as seen by `procfs`.
+#[cfg(target_os="linux")]
+fn pidfd_pid(procfs: &Procfs, pidfd: BorrowedFd<'_>) -> Result<u32, ProcfsAuxvError> {
+    let mut fdinfo = String::new();
+    File::open(procfs.path(Target::Current, &format!("fdinfo/{}", pidfd.as_raw_fd())))
+        .and_then(|mut f| f.read_to_string(&mut fdinfo))
+        .map_err(map_io_err)?;
+
+    match parse_fdinfo_pid(&fdinfo) {
+        // the process has exited
+        Some(-1) => Err(ProcfsAuxvError::ProcessExited),
+        // 0 is a process in another pid namespace, which isn't in our /proc
+        Some(pid) if pid > 0 => Ok(pid as u32),
+        _ => Err(ProcfsAuxvError::IoError)
+    }
+}
+
+/// Find the `Pid:` field in the contents of `/proc/self/fdinfo/<pidfd>`.
+#[cfg(target_os="linux")]
+fn parse_fdinfo_pid(fdinfo: &str) -> Option<i64> {
+    fdinfo.lines()
+        .find(|line| line.starts_with("Pid:"))
+        .and_then(|line| line["Pid:".len()..].trim().parse().ok())
+}
+
+/// True if the process `pidfd` refers to hasn't exited, using `pidfd_send_signal(2)` with no
+/// signal.
+#[cfg(target_os="linux")]
+fn pidfd_is_alive(pidfd: BorrowedFd<'_>) -> Result<bool, ProcfsAuxvError> {
+    let ret = unsafe {
+        syscall(SYS_PIDFD_SEND_SIGNAL, pidfd.as_raw_fd() as c_long, 0 as c_long,
+                std::ptr::null::<u8>(), 0 as c_long)
+    };
+    if ret == 0 {
+        return Ok(true);
+    }
+
+    match std::io::Error::last_os_error().raw_os_error() {
+        Some(ESRCH) => Ok(false),
+        // alive, just not ours to signal
+        Some(EPERM) => Ok(true),
+        _ => Err(ProcfsAuxvError::IoError)
+    }
+}
+
+// the same on every architecture
+#[cfg(target_os="linux")]
+const SYS_PIDFD_SEND_SIGNAL: c_long = 424;
+#[cfg(target_os="linux")]
+const SYS_PIDFD_OPEN: c_long = 434;
+#[cfg(target_os="linux")]
+const EPERM: i32 = 1;
+#[cfg(target_os="linux")]
+const ESRCH: i32 = 3;
+#[cfg(all(target_os="linux", not(any(target_arch="mips", target_arch="mips64", target_arch="sparc64"))))]
+const ENOSYS: i32 = 38;
+#[cfg(all(target_os="linux", any(target_arch="mips", target_arch="mips64")))]
+const ENOSYS: i32 = 89;
+#[cfg(all(target_os="linux", target_arch="sparc64"))]
+const ENOSYS: i32 = 90;
+
+#[cfg(target_os="linux")]
+extern "C" {
+    fn syscall(num: c_long, ...) -> c_long;
+}
+
+/// How much `ProcfsAuxvReader` reads at first: more than any aux vector Linux currently produces.
+const INITIAL_READ_BUFFER_BYTES: usize = 1024;
+
+/// The most `ProcfsAuxvReader` will read, so that reading something that isn't an auxv file (like
+/// a device in a fake procfs) doesn't grow the buffer without limit.
+pub const MAX_READ_BUFFER_BYTES: usize = 16 * INITIAL_READ_BUFFER_BYTES;
+
+/// Parse the whole contents of an auxv file, failing if any of it is invalid.
+fn parse_whole_file(bytes: &[u8], class: ElfClass) -> Result<AuxvSnapshot, ProcfsAuxvError> {
+    if bytes.is_empty() {
+        return Ok(AuxvSnapshot::default());
+    }
+
+    iterate_bytes_auxv_class::<NativeEndian>(bytes, class)
+        .map(|r| r.map_err(|_| ProcfsAuxvError::InvalidFormat))
+        .collect::<Result<Vec<_>, _>>()
+        .map(AuxvSnapshot::new)
+}
+
+/// The most bytes of `/proc/self/auxv` `search_procfs_auxv_array` will read: 64 pairs.
+pub const MAX_PROCFS_AUXV_BYTES: usize = 64 * 2 * std::mem::size_of::<AuxvType>();
+
+/// Read from the procfs auxv file and look for the specified keys, without allocating.
+///
+/// The file is read into a buffer on the stack of `MAX_PROCFS_AUXV_BYTES`, and searched with
+/// `search_auxv`: each element of the result is the value of the key at the same index in `keys`.
+/// An aux vector that doesn't fit in the buffer is an `InvalidFormat` error.
+pub fn search_procfs_auxv_array<const N: usize>(keys: [AuxvType; N])
+                                                -> Result<[Option<AuxvType>; N], ProcfsAuxvError> {
+    // not via `Procfs`, whose paths are allocated
+    let file = File::open("/proc/self/auxv").map_err(map_io_err)?;
+    search_file_array(file, keys)
+}
+
+/// Read an auxv file into a buffer on the stack and search it.
+fn search_file_array<const N: usize>(mut file: File, keys: [AuxvType; N])
+                                     -> Result<[Option<AuxvType>; N], ProcfsAuxvError> {
+    let mut buf = [0_u8; MAX_PROCFS_AUXV_BYTES];
+    let len = read_fully(&mut file, &mut buf)?;
+    // a full buffer might not be all of it, and the keys might be in the part that didn't fit
+    if len == buf.len() && read_fully(&mut file, &mut [0_u8; 1])? != 0 {
+        return Err(ProcfsAuxvError::InvalidFormat);
+    }
+
+    let pairs = iterate_bytes_auxv::<NativeEndian>(&buf[..len])
+        .map(|r| r.map_err(|_| ProcfsAuxvError::InvalidFormat));
+    try_search_auxv(pairs, keys)
+}
+
+/// Read from `file` until `buf` is full or there's nothing left, returning how much was read.
+fn read_fully(file: &mut File, buf: &mut [u8]) -> Result<usize, ProcfsAuxvError> {
+    let mut len = 0;
+    while len < buf.len() {
+        match file.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(map_io_err(e))
+        }
+    }
+    Ok(len)
 }
 
 /// Iterate over the contents of the procfs auxv file..
@@ -57,16 +544,125 @@ pub fn search_procfs_auxv(keys: &[AuxvType])
 /// Note that the type iterated over is also a Result because further I/O errors
 /// could occur at any time.
 pub fn iterate_procfs_auxv(pid: u32) -> Result<ProcfsAuxvIter<NativeEndian, File>, ProcfsAuxvError> {
-    iterate_path::<NativeEndian>(&Path::new(&format!("/proc/{}/auxv", pid)))
+    Procfs::new().iterate_auxv(Target::Pid(pid))
+}
+
+/// A memory mapping from `/proc/<pid>/maps`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryMapping {
+    /// The first address in the mapping
+    pub start: u64,
+    /// The address just past the end of the mapping
+    pub end: u64,
+    /// Permissions, e.g. `r-xp`
+    pub perms: String,
+    /// The offset into the mapped file
+    pub offset: u64,
+    /// The mapped file, or a pseudo-path like `[stack]` or `[vdso]`. Empty for anonymous mappings.
+    pub path: String,
+}
+
+impl MemoryMapping {
+    /// True if `addr` is within the mapping.
+    pub fn contains(&self, addr: u64) -> bool {
+        addr >= self.start && addr < self.end
+    }
+}
+
+/// List the pids of every process in `/proc`.
+pub fn list_procfs_pids() -> Result<Vec<u32>, ProcfsAuxvError> {
+    Procfs::new().list_pids()
+}
+
+/// List the thread ids of a process from `/proc/<pid>/task`.
+pub fn list_procfs_tasks(pid: u32) -> Result<Vec<u32>, ProcfsAuxvError> {
+    Procfs::new().list_tasks(pid)
+}
+
+/// Read the memory mappings of a process from `/proc/<pid>/maps`.
+pub fn read_procfs_maps(pid: u32) -> Result<Vec<MemoryMapping>, ProcfsAuxvError> {
+    Procfs::new().read_maps(Target::Pid(pid))
+}
+
+/// Parse a line like `7ffd2a1e5000-7ffd2a1e7000 r-xp 00000000 00:00 0    [vdso]`.
+fn parse_maps_line(line: &str) -> Option<MemoryMapping> {
+    let mut fields = line.splitn(6, ' ');
+    let mut range = fields.next()?.splitn(2, '-');
+    let start = u64::from_str_radix(range.next()?, 16).ok()?;
+    let end = u64::from_str_radix(range.next()?, 16).ok()?;
+    let perms = fields.next()?.to_owned();
+    let offset = u64::from_str_radix(fields.next()?, 16).ok()?;
+    // device and inode
+    fields.next()?;
+    fields.next()?;
+    let path = fields.next().unwrap_or("").trim_start().to_owned();
+
+    Some(MemoryMapping { start, end, perms, offset, path })
+}
+
+/// Where the kernel set up a process's initial stack, from `/proc/<pid>/stat`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackLayout {
+    /// The initial stack pointer, which points at `argc` (`startstack`)
+    pub start_stack: u64,
+    /// The start of the argument strings (`arg_start`)
+    pub arg_start: u64,
+    /// The end of the argument strings (`arg_end`)
+    pub arg_end: u64,
+    /// The start of the environment strings (`env_start`)
+    pub env_start: u64,
+    /// The end of the environment strings (`env_end`)
+    pub env_end: u64,
+}
+
+/// Read the initial stack layout of a process from `/proc/<pid>/stat`.
+///
+/// The kernel only shows these addresses to processes allowed to `ptrace` the target; otherwise
+/// they are 0.
+pub fn read_procfs_stack_layout(pid: u32) -> Result<StackLayout, ProcfsAuxvError> {
+    Procfs::new().read_stack_layout(Target::Pid(pid))
+}
+
+/// Pick the stack fields out of a `stat` line.
+fn parse_stat_stack_layout(stat: &str) -> Option<StackLayout> {
+    Some(StackLayout {
+        start_stack: stat_field(stat, 28)?,
+        arg_start: stat_field(stat, 48)?,
+        arg_end: stat_field(stat, 49)?,
+        env_start: stat_field(stat, 50)?,
+        env_end: stat_field(stat, 51)?,
+    })
+}
+
+/// Field `n` (numbered from 1, as in `proc(5)`) of a `stat` line, if it's a number.
+fn stat_field(stat: &str, n: usize) -> Option<u64> {
+    // the command name in field 2 is in parentheses and can contain anything, including spaces
+    // and parentheses, so start after the last ')'. The next field is field 3.
+    stat[stat.rfind(')')? + 1..]
+        .split_whitespace()
+        .nth(n.checked_sub(3)?)
+        .and_then(|f| f.parse().ok())
 }
 
 /// Errors from reading `/proc/self/auxv`.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ProcfsAuxvError {
     /// an io error was encountered
     IoError,
     /// the auxv data is invalid
-    InvalidFormat
+    InvalidFormat,
+    /// the process exited, so its pid may now belong to another process
+    ProcessExited,
+    /// the file couldn't be read for lack of permission, as with other users' processes
+    PermissionDenied
+}
+
+/// Tell a lack of permission apart from other I/O errors.
+fn map_io_err(e: std::io::Error) -> ProcfsAuxvError {
+    match e.kind() {
+        std::io::ErrorKind::PermissionDenied => ProcfsAuxvError::PermissionDenied,
+        _ => ProcfsAuxvError::IoError
+    }
 }
 
 /// An iterator across auxv pairs froom procfs.
@@ -81,16 +677,16 @@ pub struct ProcfsAuxvIter<B: ByteOrder, R: Read> {
 fn iterate_path<B: ByteOrder>(path: &Path)
                               -> Result<ProcfsAuxvIter<B, File>, ProcfsAuxvError> {
     let input = File::open(path)
-        .map_err(|_| ProcfsAuxvError::IoError)
-        .map(|f| BufReader::new(f))?;
+        .map_err(map_io_err)
+        .map(BufReader::new)?;
 
     let pair_size = 2 * std::mem::size_of::<AuxvType>();
     let buf: Vec<u8> = Vec::with_capacity(pair_size);
 
     Ok(ProcfsAuxvIter::<B, File> {
-        pair_size: pair_size,
-        buf: buf,
-        input: input,
+        pair_size,
+        buf,
+        input,
         keep_going: true,
         phantom_byteorder: PhantomData
     })
@@ -124,7 +720,7 @@ impl<B: ByteOrder, R: Read> Iterator for ProcfsAuxvIter<B, R> {
 
                     read_bytes += n;
                 }
-                Err(_) => return Some(Err(ProcfsAuxvError::IoError))
+                Err(e) => return Some(Err(map_io_err(e)))
             }
         }
 
@@ -151,7 +747,7 @@ impl<B: ByteOrder, R: Read> Iterator for ProcfsAuxvIter<B, R> {
     }
 }
 
-fn read_long<B: ByteOrder> (reader: &mut Read) -> std::io::Result<AuxvType>{
+fn read_long<B: ByteOrder> (reader: &mut dyn Read) -> std::io::Result<AuxvType>{
     match std::mem::size_of::<AuxvType>() {
         4 => reader.read_u32::<B>().map(|u| u as AuxvType),
         8 => reader.read_u64::<B>().map(|u| u as AuxvType),
@@ -164,11 +760,13 @@ fn read_long<B: ByteOrder> (reader: &mut Read) -> std::io::Result<AuxvType>{
 mod tests {
     use std::path::Path;
 
-    use super::iterate_path;
+    use super::{iterate_path, parse_maps_line, parse_stat_stack_layout, parse_whole_file,
+                search_file_array, MemoryMapping, ProcfsAuxvReader, StackLayout,
+                MAX_PROCFS_AUXV_BYTES};
     #[cfg(target_pointer_width="64")]
     use super::ProcfsAuxvError;
     use super::byteorder::*;
-    use super::super::AuxvPair;
+    use super::super::{AuxvPair, AuxvType, ElfClass};
 
     #[test]
     #[cfg(target_pointer_width="64")]
@@ -318,6 +916,42 @@ mod tests {
         assert_eq!(ProcfsAuxvError::InvalidFormat, iter.next().unwrap().unwrap_err());
     }
 
+    #[test]
+    fn test_parse_maps_line() {
+        assert_eq!(Some(MemoryMapping {
+            start: 0x7ffd_2a1e_5000,
+            end: 0x7ffd_2a1e_7000,
+            perms: "r-xp".to_owned(),
+            offset: 0,
+            path: "[vdso]".to_owned()
+        }), parse_maps_line("7ffd2a1e5000-7ffd2a1e7000 r-xp 00000000 00:00 0                          [vdso]"));
+        assert_eq!(Some(MemoryMapping {
+            start: 0x5570_0000_0000,
+            end: 0x5570_0002_1000,
+            perms: "r--p".to_owned(),
+            offset: 0x1000,
+            path: "/usr/bin/some file (deleted)".to_owned()
+        }), parse_maps_line("557000000000-557000021000 r--p 00001000 fd:01 1234   /usr/bin/some file (deleted)"));
+        assert_eq!("", parse_maps_line("7f0e92100000-7f0e92180000 rw-p 00000000 00:00 0 ").unwrap().path);
+        assert_eq!(None, parse_maps_line("not a mapping"));
+    }
+
+    #[test]
+    fn test_parse_stat_stack_layout() {
+        let stat = "22979 (my (odd) cmd) R 22974 22979 22974 0 -1 4194304 81 0 0 0 0 0 0 0 20 0 1 0 \
+                    179211 2703360 321 18446744073709551615 94915713454080 94915713473961 \
+                    140722393795232 0 0 0 0 0 0 0 0 0 17 0 0 0 0 0 0 94915713489968 94915713491584 \
+                    94915797164032 140722393802142 140722393802162 140722393802162 140722393804779 0\n";
+        assert_eq!(Some(StackLayout {
+            start_stack: 140722393795232,
+            arg_start: 140722393802142,
+            arg_end: 140722393802162,
+            env_start: 140722393802162,
+            env_end: 140722393804779,
+        }), parse_stat_stack_layout(stat));
+        assert_eq!(None, parse_stat_stack_layout("22979 (cat) R 22974"));
+    }
+
     #[test]
     #[cfg(target_pointer_width="32")]
     fn test_iterate_auxv_path_rpi3_arm() {
@@ -364,4 +998,105 @@ mod tests {
         assert_eq!(None, iter.next());
     }
 
+    #[test]
+    #[cfg(target_pointer_width="64")]
+    fn test_reader_reuses_buffer() {
+        let mut reader = ProcfsAuxvReader::new();
+        let fixture = Path::new("src/test-data/linux-x64-i7-6850k.auxv");
+        let expected = ::std::fs::read(fixture).unwrap();
+
+        assert_eq!(&expected[..], reader.read_path(fixture).unwrap());
+        let ptr = reader.buf.as_ptr();
+        assert_eq!(&expected[..], reader.read_path(fixture).unwrap());
+        assert_eq!(ptr, reader.buf.as_ptr());
+
+        let snapshot = parse_whole_file(reader.read_path(fixture).unwrap(), ElfClass::Elf64).unwrap();
+        assert_eq!(18, snapshot.len());
+        assert_eq!(Some(4096), snapshot.get(6));
+    }
+
+    #[test]
+    fn test_reader_grows_buffer() {
+        let mut reader = ProcfsAuxvReader::new();
+        reader.buf.resize(8, 0);
+        let fixture = Path::new("src/test-data/macos-virtualbox-linux-x86-4850HQ.auxv");
+
+        assert_eq!(&::std::fs::read(fixture).unwrap()[..], reader.read_path(fixture).unwrap());
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn test_reader_endless_file_invalidformat() {
+        let mut reader = ProcfsAuxvReader::new();
+
+        assert_eq!(Err(super::ProcfsAuxvError::InvalidFormat), reader.read_path(Path::new("/dev/zero")));
+        assert_eq!(super::MAX_READ_BUFFER_BYTES, reader.buf.len());
+    }
+
+    #[test]
+    fn test_map_io_err_permission_denied() {
+        use std::io::{Error, ErrorKind};
+
+        assert_eq!(super::ProcfsAuxvError::PermissionDenied,
+                   super::map_io_err(Error::from(ErrorKind::PermissionDenied)));
+        assert_eq!(super::ProcfsAuxvError::IoError, super::map_io_err(Error::from(ErrorKind::NotFound)));
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn test_parse_fdinfo_pid() {
+        let fdinfo = "pos:\t0\nflags:\t02000002\nmnt_id:\t15\nino:\t1057\nPid:\t1234\nNSpid:\t1234\n";
+        assert_eq!(Some(1234), super::parse_fdinfo_pid(fdinfo));
+        assert_eq!(Some(-1), super::parse_fdinfo_pid("Pid:\t-1\nNSpid:\t-1\n"));
+        assert_eq!(None, super::parse_fdinfo_pid("pos:\t0\n"));
+    }
+
+    #[test]
+    fn test_parse_whole_file_empty() {
+        assert!(parse_whole_file(&[], ElfClass::Elf64).unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(target_pointer_width="64")]
+    fn test_parse_whole_file_truncated() {
+        let mut reader = ProcfsAuxvReader::new();
+        let fixture = Path::new("src/test-data/linux-x64-i7-6850k-mangled-truncated-entry.auxv");
+
+        assert_eq!(ProcfsAuxvError::InvalidFormat,
+                   parse_whole_file(reader.read_path(fixture).unwrap(), ElfClass::Elf64).unwrap_err());
+    }
+
+    /// A file of `(6, 4096)` pairs and a final `AT_NULL`, `len` bytes long in all.
+    fn write_repeated_pairs(name: &str, len: usize) -> std::path::PathBuf {
+        let word = std::mem::size_of::<AuxvType>();
+        let mut bytes = vec![0_u8; len];
+        for pair in bytes[..len - 2 * word].chunks_exact_mut(2 * word) {
+            NativeEndian::write_uint(&mut pair[..word], 6, word);
+            NativeEndian::write_uint(&mut pair[word..], 4096, word);
+        }
+
+        let path = std::env::temp_dir().join(format!("auxv-{}-{}", name, std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_search_file_array_exactly_full() {
+        let path = write_repeated_pairs("exactly-full", MAX_PROCFS_AUXV_BYTES);
+        let result = search_file_array(std::fs::File::open(&path).unwrap(), [6]);
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(Ok([Some(4096)]), result);
+    }
+
+    #[test]
+    fn test_search_file_array_too_big_invalidformat() {
+        // the key is right at the start, but the rest doesn't fit
+        let len = MAX_PROCFS_AUXV_BYTES + 4 * std::mem::size_of::<AuxvType>();
+        let path = write_repeated_pairs("too-big", len);
+        let result = search_file_array(std::fs::File::open(&path).unwrap(), [6]);
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(Err(super::ProcfsAuxvError::InvalidFormat), result);
+    }
 }
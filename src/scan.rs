@@ -0,0 +1,225 @@
+//! Read the aux vector of every process on the system.
+//!
+//! `ProcessScanner` lists the pids in procfs and reads each one's auxv, executable path and ELF
+//! class into a `ProcessAuxv`, spread across several threads. The result answers questions like
+//! which processes are 32-bit (`ProcessAuxv::is_32_bit`) or running setuid
+//! (`ProcessAuxv::is_secure`).
+//!
+//! Not every process can be read, and a scan of a busy system never sees a consistent snapshot, so
+//! each process has its own `Result`:
+//!
+//! - other users' processes usually can't be read without privileges, which is
+//!   `PermissionDenied`
+//! - kernel threads have no aux vector or executable, and are reported with an empty snapshot
+//! - processes that exit part way through are `ProcessExited` when a pidfd is used to check (see
+//!   the `procfs` module), or whatever error the read happened to hit otherwise
+//!
+//! A 32-bit process's aux vector has 32-bit entries, even on a 64-bit system, so each process's
+//! class is worked out from the ELF header of its `exe` before parsing it. If the executable can't
+//! be read, its aux vector is assumed to be the same class as the current process.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use super::{AuxvType, ElfClass, AT_SECURE};
+use super::elf::elf_class;
+use super::procfs::{Procfs, ProcfsAuxvError, ProcfsAuxvReader, Target};
+use super::snapshot::AuxvSnapshot;
+
+/// What a scan found out about one process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessAuxv {
+    /// The pid, as seen by the procfs being scanned
+    pub pid: u32,
+    /// Where `exe` points, if it could be read
+    pub exe: Option<PathBuf>,
+    /// The class of the executable, if its ELF header could be read
+    pub class: Option<ElfClass>,
+    /// True if this is a kernel thread
+    pub kernel_thread: bool,
+    /// The aux vector, or why it couldn't be read
+    pub auxv: Result<AuxvSnapshot, ProcfsAuxvError>,
+}
+
+impl ProcessAuxv {
+    /// True if the executable is 32-bit.
+    pub fn is_32_bit(&self) -> bool {
+        self.class == Some(ElfClass::Elf32)
+    }
+
+    /// Whether the kernel set `AT_SECURE`, which it does for setuid and setgid executables, and
+    /// others that run with more privilege than whoever started them. `None` if the aux vector
+    /// couldn't be read.
+    pub fn is_secure(&self) -> Option<bool> {
+        self.value(AT_SECURE).map(|v| v != 0)
+    }
+
+    /// Look up `key` in the aux vector, if it could be read.
+    pub fn value(&self, key: AuxvType) -> Option<AuxvType> {
+        self.auxv.as_ref().ok().and_then(|snapshot| snapshot.get(key))
+    }
+}
+
+/// Scans every process in a procfs.
+#[derive(Debug, Clone)]
+pub struct ProcessScanner {
+    procfs: Procfs,
+    threads: usize,
+    use_pidfd: bool,
+    pidfd_supported: bool,
+}
+
+impl Default for ProcessScanner {
+    fn default() -> ProcessScanner {
+        ProcessScanner::new()
+    }
+}
+
+impl ProcessScanner {
+    /// Scan `/proc`, with as many threads as there are CPUs.
+    pub fn new() -> ProcessScanner {
+        ProcessScanner::with_procfs(Procfs::new())
+    }
+
+    /// Scan the given procfs.
+    ///
+    /// Pidfds refer to pids in the current process's pid namespace, so they're only used if
+    /// `procfs` is `/proc` and the kernel has them; use `use_pidfd` to change that.
+    pub fn with_procfs(procfs: Procfs) -> ProcessScanner {
+        #[cfg(target_os = "linux")]
+        let pidfd_supported = super::procfs::pidfd_supported();
+        #[cfg(not(target_os = "linux"))]
+        let pidfd_supported = false;
+        ProcessScanner::with_pidfd_support(procfs, pidfd_supported)
+    }
+
+    fn with_pidfd_support(procfs: Procfs, pidfd_supported: bool) -> ProcessScanner {
+        let use_pidfd = pidfd_supported && procfs.root() == Path::new("/proc");
+        ProcessScanner {
+            procfs,
+            threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            use_pidfd,
+            pidfd_supported,
+        }
+    }
+
+    /// Use `threads` threads (at least one).
+    pub fn threads(mut self, threads: usize) -> ProcessScanner {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Whether to check each read with a pidfd, so that a process that exits is reported as
+    /// `ProcessExited` rather than possibly mixed up with whatever reuses its pid. This needs
+    /// Linux 5.3 or later, and is ignored elsewhere.
+    pub fn use_pidfd(mut self, use_pidfd: bool) -> ProcessScanner {
+        self.use_pidfd = use_pidfd && self.pidfd_supported;
+        self
+    }
+
+    /// Scan every process, returning them in pid order.
+    ///
+    /// This only fails if the list of processes can't be read.
+    pub fn scan(&self) -> Result<Vec<ProcessAuxv>, ProcfsAuxvError> {
+        let pids = self.procfs.list_pids()?;
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(pids.len()));
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(pids.len()) {
+                scope.spawn(|| {
+                    // each thread reuses its own buffer for all of its processes
+                    let mut reader = self.procfs.auxv_reader();
+                    let mut found = Vec::new();
+                    while let Some(&pid) = pids.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        found.push(self.scan_pid_with(&mut reader, pid));
+                    }
+                    results.lock().unwrap().extend(found);
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_unstable_by_key(|p| p.pid);
+        Ok(results)
+    }
+
+    /// Scan one process.
+    pub fn scan_pid(&self, pid: u32) -> ProcessAuxv {
+        self.scan_pid_with(&mut self.procfs.auxv_reader(), pid)
+    }
+
+    fn scan_pid_with(&self, reader: &mut ProcfsAuxvReader, pid: u32) -> ProcessAuxv {
+        let target = Target::Pid(pid);
+        if self.procfs.is_kernel_thread(target).unwrap_or(false) {
+            return ProcessAuxv {
+                pid,
+                exe: None,
+                class: None,
+                kernel_thread: true,
+                auxv: Ok(AuxvSnapshot::default()),
+            };
+        }
+
+        // opened first, so the check after reading auxv covers the executable too
+        #[cfg(target_os = "linux")]
+        let pidfd = if self.use_pidfd {
+            match super::procfs::open_pidfd(pid) {
+                Ok(pidfd) => Some(pidfd),
+                Err(e) => {
+                    return ProcessAuxv { pid, exe: None, class: None, kernel_thread: false, auxv: Err(e) };
+                }
+            }
+        } else {
+            None
+        };
+
+        let exe_path = self.procfs.path(target, "exe");
+        let exe = fs::read_link(&exe_path).ok();
+        let class = read_elf_class(&exe_path);
+        let auxv_class = class.unwrap_or_else(ElfClass::native);
+
+        #[cfg(target_os = "linux")]
+        let auxv = match pidfd {
+            Some(ref pidfd) => {
+                use std::os::fd::AsFd;
+                reader.snapshot_pidfd(pidfd.as_fd(), auxv_class)
+            }
+            None => reader.snapshot_pid(pid, auxv_class),
+        };
+        #[cfg(not(target_os = "linux"))]
+        let auxv = reader.snapshot_pid(pid, auxv_class);
+
+        ProcessAuxv { pid, exe, class, kernel_thread: false, auxv }
+    }
+}
+
+/// The class of the ELF file at `path`.
+fn read_elf_class(path: &Path) -> Option<ElfClass> {
+    let mut ident = [0_u8; 5];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut ident))
+        .ok()
+        .and_then(|_| elf_class(&ident))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::super::AT_PHDR;
+    use super::super::procfs::Procfs;
+    use super::ProcessScanner;
+
+    #[test]
+    fn test_scan_without_pidfd_support_reads_pid() {
+        // as on a kernel older than 5.3, where pidfd_open fails with ENOSYS
+        let scanner = ProcessScanner::with_pidfd_support(Procfs::new(), false).use_pidfd(true);
+        assert!(!scanner.use_pidfd);
+
+        let process = scanner.scan_pid(std::process::id());
+        assert!(process.value(AT_PHDR).is_some());
+    }
+}
@@ -0,0 +1,225 @@
+//! Look up auxv values from a signal handler.
+//!
+//! A crash handler often wants `AT_SYSINFO_EHDR`, `AT_PHDR` or `AT_HWCAP`, but almost nothing else
+//! in this crate is safe to use there: `search_procfs_auxv` allocates a `HashMap` and a
+//! `BufReader`, `cached_auxv` may take a lock the first time, and the stack iterator relies on
+//! `environ`, which the interrupted code might have been in the middle of changing.
+//!
+//! `signal_safe_auxv_value` is async-signal-safe: it doesn't allocate, lock or panic, and it leaves
+//! `errno` as it found it. Values are kept in fixed-size static storage made of atomics, which is
+//! filled from the auxv found by the `init-array` constructor if that feature is on, and otherwise
+//! by `read(2)`ing `/proc/self/auxv` into a buffer on the stack. `open`, `read` and `close` are
+//! all on the POSIX list of async-signal-safe functions, so the storage can be filled from inside a
+//! handler, but it's better to call `prepare_signal_safe_auxv` when installing the handler: then
+//! the handler only does atomic loads, and still works if the process later runs out of file
+//! descriptors or is sandboxed away from `/proc`.
+//!
+//! If a signal arrives while the storage is being filled on the same thread, the handler doesn't
+//! wait for it (which would deadlock), but reads its own copy into a buffer on its stack instead.
+//!
+//! Only keys below `SIGNAL_SAFE_KEYS` are stored, which covers every key Linux defines. Larger
+//! keys are reported as `NotFound`.
+
+use std::ffi::{c_char, c_int, c_void};
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+
+use super::AuxvType;
+
+/// Keys below this are kept in the static storage.
+pub const SIGNAL_SAFE_KEYS: usize = 64;
+
+/// The most pairs read from `/proc/self/auxv`, including `AT_NULL`.
+const MAX_PROCFS_PAIRS: usize = 64;
+
+const O_RDONLY: c_int = 0;
+const EINTR: c_int = 4;
+
+extern "C" {
+    fn open(path: *const c_char, flags: c_int, ...) -> c_int;
+    fn read(fd: c_int, buf: *mut c_void, count: usize) -> isize;
+    fn close(fd: c_int) -> c_int;
+    fn __errno_location() -> *mut c_int;
+}
+
+/// Errors from the signal-safe lookup.
+#[derive(Debug, PartialEq)]
+pub enum SignalSafeAuxvError {
+    /// there's no startup auxv, and `/proc/self/auxv` couldn't be read
+    NotAvailable,
+    /// `/proc/self/auxv` had no `AT_NULL` within `MAX_PROCFS_PAIRS` pairs
+    InvalidFormat,
+    /// the key isn't in the aux vector
+    NotFound
+}
+
+const STATE_EMPTY: u8 = 0;
+const STATE_FILLING: u8 = 1;
+const STATE_READY: u8 = 2;
+
+static STATE: AtomicU8 = AtomicU8::new(STATE_EMPTY);
+static VALUES: [AtomicUsize; SIGNAL_SAFE_KEYS] = [const { AtomicUsize::new(0) }; SIGNAL_SAFE_KEYS];
+static PRESENT: [AtomicBool; SIGNAL_SAFE_KEYS] = [const { AtomicBool::new(false) }; SIGNAL_SAFE_KEYS];
+
+/// Fill the storage used by `signal_safe_auxv_value`, if it isn't already.
+///
+/// Call this before installing a signal handler that uses `signal_safe_auxv_value`. It's
+/// async-signal-safe too, but doing the I/O up front means the handler never has to.
+pub fn prepare_signal_safe_auxv() -> Result<(), SignalSafeAuxvError> {
+    if STATE.compare_exchange(STATE_EMPTY, STATE_FILLING, Ordering::Acquire, Ordering::Acquire)
+        .is_err() {
+        // either ready, or another thread is filling it and will be done soon
+        return Ok(());
+    }
+
+    let result = fill_storage();
+    STATE.store(if result.is_ok() { STATE_READY } else { STATE_EMPTY }, Ordering::Release);
+    result
+}
+
+/// Look up `key` in the current process's aux vector. This is async-signal-safe.
+///
+/// If `prepare_signal_safe_auxv` hasn't been called, this does it first.
+pub fn signal_safe_auxv_value(key: AuxvType) -> Result<AuxvType, SignalSafeAuxvError> {
+    if STATE.load(Ordering::Acquire) != STATE_READY {
+        with_errno_preserved(prepare_signal_safe_auxv)?;
+
+        if STATE.load(Ordering::Acquire) != STATE_READY {
+            // the thread filling the storage may be the one this signal interrupted
+            let mut buf = [0; 2 * MAX_PROCFS_PAIRS];
+            let words = with_errno_preserved(|| read_procfs_auxv(&mut buf))?;
+            return pairs(words)
+                .find(|&(k, _)| k == key)
+                .map(|(_, v)| v)
+                .ok_or(SignalSafeAuxvError::NotFound);
+        }
+    }
+
+    stored_value(key).ok_or(SignalSafeAuxvError::NotFound)
+}
+
+/// Look up `key` in `VALUES` and `PRESENT`.
+#[allow(clippy::unnecessary_cast)]
+fn stored_value(key: AuxvType) -> Option<AuxvType> {
+    if (key as u64) < SIGNAL_SAFE_KEYS as u64 && PRESENT[key as usize].load(Ordering::Relaxed) {
+        Some(VALUES[key as usize].load(Ordering::Relaxed) as AuxvType)
+    } else {
+        None
+    }
+}
+
+/// Copy the aux vector into `VALUES` and `PRESENT`. Only called with `STATE` set to filling.
+fn fill_storage() -> Result<(), SignalSafeAuxvError> {
+    #[cfg(feature = "init-array")]
+    {
+        if let Some(iter) = super::stack::iterate_startup_auxv() {
+            for pair in iter {
+                store(pair.key, pair.value);
+            }
+            return Ok(());
+        }
+    }
+
+    let mut buf = [0; 2 * MAX_PROCFS_PAIRS];
+    let words = read_procfs_auxv(&mut buf)?;
+    for (key, value) in pairs(words) {
+        store(key, value);
+    }
+    Ok(())
+}
+
+/// Store one pair, unless the key is too large or already there: the first one wins.
+#[allow(clippy::unnecessary_cast)]
+fn store(key: AuxvType, value: AuxvType) {
+    if (key as u64) < SIGNAL_SAFE_KEYS as u64 && stored_value(key).is_none() {
+        VALUES[key as usize].store(value as usize, Ordering::Relaxed);
+        PRESENT[key as usize].store(true, Ordering::Relaxed);
+    }
+}
+
+/// The pairs in `words`, up to `AT_NULL`.
+fn pairs(words: &[AuxvType]) -> impl Iterator<Item = (AuxvType, AuxvType)> + '_ {
+    words.chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .take_while(|&(key, _)| key != 0)
+}
+
+/// Read `/proc/self/auxv` into `buf` with raw syscalls, returning the words read.
+///
+/// The words are checked to end with `AT_NULL`, so `pairs` sees the whole aux vector.
+fn read_procfs_auxv(buf: &mut [AuxvType]) -> Result<&[AuxvType], SignalSafeAuxvError> {
+    let fd = unsafe { open(b"/proc/self/auxv\0".as_ptr() as *const c_char, O_RDONLY) };
+    if fd < 0 {
+        return Err(SignalSafeAuxvError::NotAvailable);
+    }
+
+    let capacity = mem::size_of_val(buf);
+    let mut len = 0;
+    let result = loop {
+        if len == capacity {
+            break Ok(());
+        }
+        let n = unsafe {
+            read(fd, (buf.as_mut_ptr() as *mut u8).add(len) as *mut c_void, capacity - len)
+        };
+        if n > 0 {
+            len += n as usize;
+        } else if n == 0 {
+            break Ok(());
+        } else if unsafe { *__errno_location() } != EINTR {
+            break Err(SignalSafeAuxvError::NotAvailable);
+        }
+    };
+    unsafe { close(fd) };
+    result?;
+
+    let words = &buf[..len / mem::size_of::<AuxvType>()];
+    if words.chunks_exact(2).any(|pair| pair[0] == 0) {
+        Ok(words)
+    } else {
+        Err(SignalSafeAuxvError::InvalidFormat)
+    }
+}
+
+/// Run `f`, then put `errno` back how it was, as a signal handler must.
+fn with_errno_preserved<T, F: FnOnce() -> T>(f: F) -> T {
+    let saved = unsafe { *__errno_location() };
+    let result = f();
+    unsafe { *__errno_location() = saved };
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::AuxvType;
+    use super::{pairs, read_procfs_auxv, SignalSafeAuxvError, MAX_PROCFS_PAIRS};
+
+    #[test]
+    fn test_pairs_stops_at_null() {
+        let words: [AuxvType; 8] = [6, 4096, 16, 0xff, 0, 0, 9, 9];
+
+        assert_eq!(vec![(6, 4096), (16, 0xff)], pairs(&words).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_pairs_ignores_odd_word() {
+        let words: [AuxvType; 3] = [6, 4096, 16];
+
+        assert_eq!(vec![(6, 4096)], pairs(&words).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_read_procfs_auxv_ends_with_null() {
+        let mut buf = [0; 2 * MAX_PROCFS_PAIRS];
+        let words = read_procfs_auxv(&mut buf).unwrap();
+
+        assert!(pairs(words).any(|(key, _)| key == 6));
+    }
+
+    #[test]
+    fn test_read_procfs_auxv_too_small() {
+        let mut buf = [0; 4];
+
+        assert_eq!(SignalSafeAuxvError::InvalidFormat, read_procfs_auxv(&mut buf).unwrap_err());
+    }
+}
@@ -0,0 +1,50 @@
+//! An owned copy of an aux vector.
+//!
+//! The other modules read auxv from a particular place. `AuxvSnapshot` is what you keep once
+//! you've read it: a plain list of pairs, in the order they appeared, that can be queried by key
+//! after the original source (a process, a file) is long gone.
+
+use std::iter::FromIterator;
+
+use super::{AuxvPair, AuxvType};
+
+/// The entries of an aux vector, not including the terminating `AT_NULL`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AuxvSnapshot {
+    pairs: Vec<AuxvPair>,
+}
+
+impl AuxvSnapshot {
+    /// Create a snapshot from pairs that were already read from some source.
+    pub fn new(pairs: Vec<AuxvPair>) -> AuxvSnapshot {
+        AuxvSnapshot { pairs }
+    }
+
+    /// The pairs in the order they appeared in the aux vector.
+    pub fn pairs(&self) -> &[AuxvPair] {
+        &self.pairs
+    }
+
+    /// Look up the value for `key`. If the key appears more than once, the first one wins.
+    pub fn get(&self, key: AuxvType) -> Option<AuxvType> {
+        self.pairs.iter()
+            .find(|p| p.key == key)
+            .map(|p| p.value)
+    }
+
+    /// The number of pairs in the snapshot.
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// True if the snapshot has no pairs.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+impl FromIterator<AuxvPair> for AuxvSnapshot {
+    fn from_iter<I: IntoIterator<Item = AuxvPair>>(iter: I) -> AuxvSnapshot {
+        AuxvSnapshot::new(iter.into_iter().collect())
+    }
+}
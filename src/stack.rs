@@ -1,7 +1,7 @@
 //! Read auxv entries by chasing pointers in the ELF stack layout.
 //!
-//! The sole public function is `iterate_stack_auxv`. It iterates across the entries, exposing them via
-//! `AuxvPair`. The two fields in `AuxvPair` will be of type `AuxvType`.
+//! The main public function is `iterate_stack_auxv`. It iterates across the entries, exposing them
+//! via `AuxvPair`. The two fields in `AuxvPair` will be of type `AuxvType`.
 //!
 //! **Only use this code when all of the following are satisfied**:
 //!
@@ -25,13 +25,95 @@
 //! numbers for a while before mercifully exiting normally. On Windows, the function is not
 //! available because their names are not POSIX compatible so it wouldn't even compile, and so the
 //! example prints nothing.
+//!
+//! # Checked iteration
+//!
+//! `iterate_stack_auxv_checked` is a safe alternative for when you can't be sure the environment
+//! hasn't been touched. It finds the `[stack]` mapping in `/proc/self/maps` first, and only reads
+//! memory inside it: if `environ` has been moved off the stack, or the walk runs off the end of
+//! the stack, finds an implausible key, or goes on for more entries than any kernel writes, it
+//! produces an error instead. It needs `std` and procfs.
+//!
+//! # Has `environ` moved?
+//!
+//! Rather than trusting that nothing has called `setenv` yet, `environ_is_original` checks: the
+//! kernel records the initial stack pointer (`startstack` in `/proc/self/stat`), which points at
+//! `argc`, and the original environment array is right after `argv` from there. If `environ`
+//! doesn't point there any more, `iterate_stack_auxv` would read garbage.
+//! `iterate_stack_auxv_verified` doesn't depend on `environ` at all: it finds auxv from the
+//! initial stack pointer, so it works whether or not the environment has been moved. Like the
+//! checked iterator, it only reads inside the `[stack]` mapping.
+//!
+//! # Without libc
+//!
+//! `environ` is set up by libc, so in a `#![no_main]` binary with its own `_start` there's nothing
+//! to start from. What `_start` does get is the stack pointer, which points at the start of the
+//! same layout: `argc`, then the `argv` pointers, then the `envp` pointers, then auxv.
+//! `InitialStack::from_stack_pointer` parses that into typed arguments, environment and auxv. See
+//! the `initial_stack_start` example for a binary that uses it from `_start` on x86_64 Linux.
+//!
+//! # Capturing auxv at startup
+//!
+//! With the `init-array` feature, this module registers an `.init_array` constructor, which runs
+//! before `main` and before anything has had a chance to touch the environment. glibc passes
+//! constructors `argc`, `argv` and `envp`; elsewhere the constructor uses `environ`, which hasn't
+//! been modified yet either. Either way it records where auxv is, and `iterate_startup_auxv` then
+//! works safely at any time, however the environment has been changed since.
+//!
+//! # The original arguments and environment
+//!
+//! `initial_stack` returns the `argc`, `argv` and `envp` the kernel laid out, regardless of what
+//! `environ` points at now. It uses the initial stack pointer recorded by the `init-array`
+//! constructor on glibc, and `startstack` from `/proc/self/stat` otherwise (which
+//! `prctl(PR_SET_MM)` can change, so the constructor is preferable if you need to be sure). Either
+//! way, `argc`, both arrays and auxv are checked to be inside the `[stack]` mapping before they're
+//! used, so this needs procfs even when the constructor ran.
+//!
+//! That's the original arrays, but programs that set their process title overwrite the strings
+//! they point to. With both `init-array` and `std`, the constructor also copies the arguments and
+//! environment before `main` runs, and `pristine_args` returns those copies.
 
-#[cfg(not(target_os="windows"))]
-use std;
+use std::ffi::{c_char, CStr};
+#[cfg(feature = "init-array")]
+use std::ffi::c_int;
+#[cfg(all(feature = "init-array", feature = "std"))]
+use std::ffi::CString;
+#[cfg(all(feature = "init-array", feature = "std"))]
+use std::sync::OnceLock;
+use std::mem;
+#[cfg(feature = "init-array")]
+use std::sync::atomic::{AtomicPtr, Ordering};
 
 use super::{AuxvPair, AuxvType};
+#[cfg(all(feature = "std", not(target_os="windows")))]
+use super::procfs::{read_procfs_maps, read_procfs_stack_layout};
+
+/// The most auxv entries `iterate_stack_auxv_checked` will read before giving up. The kernel
+/// writes fewer than 40 on every architecture (see `AT_VECTOR_SIZE`).
+pub const MAX_STACK_AUXV_ENTRIES: usize = 64;
+
+/// Keys larger than this are taken as a sign that what's being read isn't auxv. The largest key
+/// in use is in the low 50s.
+pub const MAX_STACK_AUXV_KEY: AuxvType = 0xff;
+
+/// Errors from the checked stack iterator.
+#[derive(Debug, PartialEq)]
+pub enum StackAuxvError {
+    /// an io error was encountered while finding the stack mapping
+    IoError,
+    /// `environ` or auxv isn't within the `[stack]` mapping, e.g. because the environment was
+    /// modified
+    NotOnStack,
+    /// an entry has an implausible key, or there's no `AT_NULL` within
+    /// `MAX_STACK_AUXV_ENTRIES` entries
+    InvalidFormat
+}
 
 /// Returns an iterator across the auxv entries.
+///
+/// # Safety
+///
+/// The environment must not have been modified since the process started; see the module docs.
 #[cfg(not(target_os="windows"))]
 pub unsafe fn iterate_stack_auxv() -> StackAuxvIter {
     StackAuxvIter {
@@ -39,6 +121,384 @@ pub unsafe fn iterate_stack_auxv() -> StackAuxvIter {
     }
 }
 
+/// Returns an iterator across the auxv entries that only reads from the `[stack]` mapping.
+///
+/// Unlike `iterate_stack_auxv`, this is safe to call whenever: a modified environment results in
+/// an error, not garbage or a crash.
+#[cfg(all(feature = "std", not(target_os="windows")))]
+pub fn iterate_stack_auxv_checked() -> Result<CheckedStackAuxvIter, StackAuxvError> {
+    let (start, end) = stack_bounds()?;
+    let word = mem::size_of::<usize>();
+
+    let mut env_entry = unsafe { environ } as usize;
+    loop {
+        if env_entry < start || env_entry >= end || !env_entry.is_multiple_of(word) {
+            return Err(StackAuxvError::NotOnStack);
+        }
+        // in bounds and aligned, so this is a read of mapped stack memory
+        if unsafe { *(env_entry as *const usize) } == 0 {
+            break;
+        }
+        env_entry += word;
+    }
+
+    Ok(CheckedStackAuxvIter {
+        auxv_key_ptr: env_entry + word,
+        end,
+        remaining: MAX_STACK_AUXV_ENTRIES,
+    })
+}
+
+/// Returns true if `environ` still points at the environment array the kernel set up on the
+/// initial stack, so `iterate_stack_auxv` can be trusted.
+#[cfg(all(feature = "std", not(target_os="windows")))]
+pub fn environ_is_original() -> Result<bool, StackAuxvError> {
+    let initial = initial_stack()?;
+    Ok(std::ptr::eq(unsafe { environ }, initial.envp() as *const *const u8))
+}
+
+/// Returns an iterator across the auxv entries, found from the initial stack pointer recorded by
+/// the kernel rather than from `environ`, so it's unaffected by changes to the environment.
+///
+/// The original environment array is still walked to find its end, so this can't help if that
+/// array itself has been modified (e.g. by `unsetenv` before anything reallocated `environ`), but
+/// as with `iterate_stack_auxv_checked`, that results in an error rather than a bad read.
+#[cfg(all(feature = "std", not(target_os="windows")))]
+pub fn iterate_stack_auxv_verified() -> Result<CheckedStackAuxvIter, StackAuxvError> {
+    let (start, end) = stack_bounds()?;
+    let initial = initial_stack_within(initial_stack_pointer()?, start, end)?;
+    Ok(CheckedStackAuxvIter {
+        auxv_key_ptr: initial.auxv as usize,
+        end,
+        remaining: MAX_STACK_AUXV_ENTRIES,
+    })
+}
+
+/// The initial stack of the current process: the `argc`, `argv` and `envp` the kernel set up,
+/// however the environment has been changed since.
+///
+/// Uses the stack pointer captured by the `init-array` constructor if there is one, and
+/// `/proc/self/stat` otherwise. Everything from `argc` to the end of auxv must be inside the
+/// `[stack]` mapping, or this returns an error. The strings `argv` and `envp` point to aren't
+/// checked, which is why `InitialStack::args` and `env` are unsafe.
+#[cfg(all(feature = "std", not(target_os="windows")))]
+pub fn initial_stack() -> Result<InitialStack, StackAuxvError> {
+    let (start, end) = stack_bounds()?;
+    initial_stack_within(initial_stack_pointer()?, start, end)
+}
+
+/// The initial stack pointer, from the `init-array` constructor or `startstack` in
+/// `/proc/self/stat`.
+#[cfg(all(feature = "std", not(target_os="windows")))]
+fn initial_stack_pointer() -> Result<usize, StackAuxvError> {
+    #[cfg(feature = "init-array")]
+    {
+        let sp = STARTUP_STACK.load(Ordering::Acquire);
+        if !sp.is_null() {
+            return Ok(sp as usize);
+        }
+    }
+
+    // 0 if the kernel didn't want to tell us, which isn't on the stack
+    read_procfs_stack_layout(std::process::id())
+        .map(|layout| layout.start_stack as usize)
+        .map_err(|_| StackAuxvError::IoError)
+}
+
+/// Parse the initial stack at `sp`, only reading inside `start..end`: `argc`, the `argv` and
+/// `envp` arrays, and auxv up to `AT_NULL` must all be in there.
+#[cfg(all(feature = "std", not(target_os="windows")))]
+fn initial_stack_within(sp: usize, start: usize, end: usize) -> Result<InitialStack, StackAuxvError> {
+    let word = mem::size_of::<usize>();
+    let read = |addr: usize| {
+        if addr < start || addr >= end || !addr.is_multiple_of(word) {
+            return Err(StackAuxvError::NotOnStack);
+        }
+        // in bounds and aligned, so this is a read of mapped stack memory
+        Ok(unsafe { *(addr as *const usize) })
+    };
+
+    let argc = read(sp)?;
+    let argv = sp + word;
+    // argv is terminated by a null pointer, then envp starts
+    let argv_end = argc.checked_mul(word)
+        .and_then(|len| argv.checked_add(len))
+        .ok_or(StackAuxvError::NotOnStack)?;
+    if read(argv_end)? != 0 {
+        return Err(StackAuxvError::InvalidFormat);
+    }
+    let envp = argv_end + word;
+
+    let mut env_entry = envp;
+    while read(env_entry)? != 0 {
+        env_entry += word;
+    }
+    let auxv = env_entry + word;
+
+    let pairs = CheckedStackAuxvIter {
+        auxv_key_ptr: auxv,
+        end,
+        remaining: MAX_STACK_AUXV_ENTRIES,
+    };
+    for pair in pairs {
+        pair?;
+    }
+
+    Ok(InitialStack {
+        argc,
+        argv: argv as *const *const c_char,
+        envp: envp as *const *const c_char,
+        auxv: auxv as *const AuxvType,
+    })
+}
+
+/// The bounds of the main thread's stack, from `/proc/self/maps`.
+#[cfg(all(feature = "std", not(target_os="windows")))]
+fn stack_bounds() -> Result<(usize, usize), StackAuxvError> {
+    let maps = read_procfs_maps(std::process::id()).map_err(|_| StackAuxvError::IoError)?;
+    maps.iter()
+        .find(|m| m.path == "[stack]")
+        .map(|m| (m.start as usize, m.end as usize))
+        .ok_or(StackAuxvError::NotOnStack)
+}
+
+/// An iterator across auxv pairs on the stack that checks every read against the stack's bounds.
+///
+/// Like the procfs iterator, the items are `Result`s: after the first error, the iterator stops.
+#[derive(Debug)]
+pub struct CheckedStackAuxvIter {
+    auxv_key_ptr: usize,
+    end: usize,
+    remaining: usize,
+}
+
+impl Iterator for CheckedStackAuxvIter {
+    type Item = Result<AuxvPair, StackAuxvError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.auxv_key_ptr == 0 {
+            // found AT_NULL or an error already
+            return None;
+        }
+
+        let pair_end = self.auxv_key_ptr.checked_add(2 * mem::size_of::<AuxvType>());
+        if self.remaining == 0 || pair_end.is_none_or(|e| e > self.end) {
+            self.auxv_key_ptr = 0;
+            return Some(Err(StackAuxvError::InvalidFormat));
+        }
+        self.remaining -= 1;
+
+        let (key, value) = unsafe {
+            let ptr = self.auxv_key_ptr as *const AuxvType;
+            (*ptr, *ptr.offset(1))
+        };
+        if key == 0 {
+            self.auxv_key_ptr = 0;
+            return None;
+        }
+        if key > MAX_STACK_AUXV_KEY {
+            self.auxv_key_ptr = 0;
+            return Some(Err(StackAuxvError::InvalidFormat));
+        }
+
+        self.auxv_key_ptr += 2 * mem::size_of::<AuxvType>();
+        Some(Ok(AuxvPair {
+            key,
+            value
+        }))
+    }
+}
+
+/// Returns an iterator across the auxv entries, as found by the `init-array` constructor.
+///
+/// Returns `None` if the constructor didn't run, e.g. because the platform doesn't run
+/// `.init_array` constructors for this kind of binary.
+#[cfg(all(feature = "init-array", not(target_os="windows")))]
+pub fn iterate_startup_auxv() -> Option<StackAuxvIter> {
+    let ptr = STARTUP_AUXV.load(Ordering::Acquire);
+    if ptr.is_null() {
+        None
+    } else {
+        Some(StackAuxvIter { auxv_key_ptr: ptr })
+    }
+}
+
+/// Returns the initial stack, as found by the `init-array` constructor.
+///
+/// Returns `None` if the constructor didn't run, or if it wasn't given `argv`: only glibc passes
+/// arguments to constructors.
+#[cfg(all(feature = "init-array", not(target_os="windows")))]
+pub fn startup_stack() -> Option<InitialStack> {
+    let sp = STARTUP_STACK.load(Ordering::Acquire);
+    if sp.is_null() {
+        None
+    } else {
+        // the constructor checked it was given argv, which is right after argc on the stack
+        Some(unsafe { InitialStack::from_stack_pointer(sp) })
+    }
+}
+
+/// Copies of a process's arguments and environment, taken before `main`.
+#[cfg(all(feature = "init-array", feature = "std"))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PristineArgs {
+    /// The arguments, starting with the program name
+    pub args: Vec<CString>,
+    /// The environment's `NAME=value` strings
+    pub env: Vec<CString>,
+}
+
+/// Returns the arguments and environment as they were before `main`, copied by the `init-array`
+/// constructor.
+///
+/// Returns `None` under the same conditions as `startup_stack`.
+#[cfg(all(feature = "init-array", feature = "std", not(target_os="windows")))]
+pub fn pristine_args() -> Option<&'static PristineArgs> {
+    PRISTINE_ARGS.get()
+}
+
+/// The start of auxv, as found by `capture_startup_auxv`.
+#[cfg(all(feature = "init-array", not(target_os="windows")))]
+static STARTUP_AUXV: AtomicPtr<AuxvType> = AtomicPtr::new(std::ptr::null_mut());
+
+/// The initial stack pointer, if `capture_startup_auxv` could work it out.
+#[cfg(all(feature = "init-array", not(target_os="windows")))]
+static STARTUP_STACK: AtomicPtr<usize> = AtomicPtr::new(std::ptr::null_mut());
+
+#[cfg(all(feature = "init-array", feature = "std", not(target_os="windows")))]
+static PRISTINE_ARGS: OnceLock<PristineArgs> = OnceLock::new();
+
+#[cfg(all(feature = "init-array", not(target_os="windows")))]
+#[used]
+#[link_section = ".init_array"]
+static CAPTURE_STARTUP_AUXV: extern "C" fn(c_int, *const *const u8, *const *const u8) =
+    capture_startup_auxv;
+
+/// Runs from `.init_array` before `main`.
+#[cfg(all(feature = "init-array", not(target_os="windows")))]
+extern "C" fn capture_startup_auxv(_argc: c_int, argv: *const *const u8, envp: *const *const u8) {
+    // only glibc passes arguments to constructors; other libcs leave garbage in those registers
+    let envp = if cfg!(target_env="gnu") { envp } else { unsafe { environ } };
+    if envp.is_null() {
+        return;
+    }
+
+    let auxv = unsafe { skip_past_null(envp) } as *mut AuxvType;
+    STARTUP_AUXV.store(auxv, Ordering::Release);
+
+    if !cfg!(target_env="gnu") || argv.is_null() {
+        return;
+    }
+    // argc is the word before argv
+    let sp = unsafe { argv.offset(-1) } as *mut usize;
+    STARTUP_STACK.store(sp, Ordering::Release);
+
+    #[cfg(feature = "std")]
+    {
+        let initial = unsafe { InitialStack::from_stack_pointer(sp) };
+        // nothing has had a chance to change the strings yet
+        let _ = PRISTINE_ARGS.set(PristineArgs {
+            args: unsafe { initial.args() }.map(CString::from).collect(),
+            env: unsafe { initial.env() }.map(CString::from).collect(),
+        });
+    }
+}
+
+/// The argument count, arguments, environment and auxv that the kernel put on a new process's
+/// stack.
+#[derive(Debug, Clone, Copy)]
+pub struct InitialStack {
+    argc: usize,
+    argv: *const *const c_char,
+    envp: *const *const c_char,
+    auxv: *const AuxvType,
+}
+
+impl InitialStack {
+    /// Parse the initial stack at `sp`, the stack pointer as it was on entry to `_start`.
+    ///
+    /// # Safety
+    ///
+    /// `sp` must be the initial stack pointer of the current process, and the `argv` and `envp`
+    /// arrays and the strings they point to must not be modified for as long as the results are
+    /// used.
+    pub unsafe fn from_stack_pointer(sp: *const usize) -> InitialStack {
+        let argc = *sp;
+        let argv = sp.offset(1) as *const *const c_char;
+        // argv is terminated by a null pointer, then envp starts
+        let envp = argv.add(argc + 1);
+
+        InitialStack {
+            argc,
+            argv,
+            envp,
+            auxv: skip_past_null(envp as *const *const u8) as *const AuxvType,
+        }
+    }
+
+    /// The number of arguments.
+    pub fn argc(&self) -> usize {
+        self.argc
+    }
+
+    /// The null-terminated array of argument pointers, as `main` would get it.
+    pub fn argv(&self) -> *const *const c_char {
+        self.argv
+    }
+
+    /// The null-terminated array of environment pointers, as `main` would get it.
+    pub fn envp(&self) -> *const *const c_char {
+        self.envp
+    }
+
+    /// Iterate over the arguments.
+    ///
+    /// # Safety
+    ///
+    /// Only the arrays are checked, not the strings, so each pointer in `argv` must still point to
+    /// a null-terminated string that lives as long as the process, as the kernel's do. Programs
+    /// that store their own pointers in `argv` (e.g. to change their process title) break that.
+    pub unsafe fn args(&self) -> CStrArrayIter {
+        CStrArrayIter { ptr: self.argv }
+    }
+
+    /// Iterate over the environment's `NAME=value` strings.
+    ///
+    /// # Safety
+    ///
+    /// As with `args`, each pointer in `envp` must still point to a null-terminated string that
+    /// lives as long as the process.
+    pub unsafe fn env(&self) -> CStrArrayIter {
+        CStrArrayIter { ptr: self.envp }
+    }
+
+    /// Iterate over the auxv entries.
+    pub fn auxv(&self) -> StackAuxvIter {
+        StackAuxvIter { auxv_key_ptr: self.auxv }
+    }
+}
+
+/// An iterator over a null-terminated array of C strings, like `argv` or `envp`.
+///
+/// See `InitialStack::args` for what it relies on.
+pub struct CStrArrayIter {
+    ptr: *const *const c_char,
+}
+
+impl Iterator for CStrArrayIter {
+    type Item = &'static CStr;
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            if (*self.ptr).is_null() {
+                return None;
+            }
+
+            let s = CStr::from_ptr(*self.ptr);
+            self.ptr = self.ptr.offset(1);
+            Some(s)
+        }
+    }
+}
+
 /// An iterator across auxv pairs from crawling the ELF stack.
 pub struct StackAuxvIter {
     auxv_key_ptr: *const AuxvType,
@@ -59,8 +519,8 @@ impl Iterator for StackAuxvIter {
             self.auxv_key_ptr = self.auxv_key_ptr.offset(2);
 
             Some(AuxvPair {
-                key: key,
-                value: value
+                key,
+                value
             })
         }
     }
@@ -80,8 +540,12 @@ extern "C" {
 /// (specifically, the key in the first key / value pair)
 #[cfg(not(target_os="windows"))]
 unsafe fn get_auxv_ptr() -> *const AuxvType {
-    let mut env_entry_ptr = environ;
+    skip_past_null(environ) as *const AuxvType
+}
 
+/// returns a pointer to the entry after the null entry that terminates the environment listing,
+/// which is the first entry of auxv
+unsafe fn skip_past_null(mut env_entry_ptr: *const *const u8) -> *const *const u8 {
     while !(*env_entry_ptr).is_null() {
         // skip the pointers to environment strings
         env_entry_ptr = env_entry_ptr.offset(1);
@@ -89,7 +553,104 @@ unsafe fn get_auxv_ptr() -> *const AuxvType {
 
     // env_entry_ptr now points at the null entry after the environment listing
     // advance it one more to point at first entry of auxv
-    env_entry_ptr = env_entry_ptr.offset(1);
+    env_entry_ptr.offset(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CheckedStackAuxvIter, InitialStack, StackAuxvError, MAX_STACK_AUXV_ENTRIES};
+    use super::super::{AuxvPair, AuxvType, AT_HWCAP};
 
-    return std::mem::transmute::<*const *const u8, *const AuxvType>(env_entry_ptr);
+    fn checked_iter(words: &[AuxvType]) -> CheckedStackAuxvIter {
+        CheckedStackAuxvIter {
+            auxv_key_ptr: words.as_ptr() as usize,
+            end: words.as_ptr() as usize + std::mem::size_of_val(words),
+            remaining: MAX_STACK_AUXV_ENTRIES,
+        }
+    }
+
+    #[test]
+    fn test_checked_iter_stops_at_at_null() {
+        let words = [AT_HWCAP, 0xbfeb_fbff, 0, 0, 99, 99];
+        assert_eq!(vec![Ok(AuxvPair { key: AT_HWCAP, value: 0xbfeb_fbff })],
+                   checked_iter(&words).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_checked_iter_bad_key_invalidformat() {
+        let words = [AT_HWCAP, 1, 0x7fff_1234, 2, 0, 0];
+        assert_eq!(vec![Ok(AuxvPair { key: AT_HWCAP, value: 1 }), Err(StackAuxvError::InvalidFormat)],
+                   checked_iter(&words).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_checked_iter_end_of_stack_invalidformat() {
+        // no AT_NULL before the end of the mapping
+        let words = [AT_HWCAP, 1, AT_HWCAP, 2];
+        assert_eq!(Some(Err(StackAuxvError::InvalidFormat)), checked_iter(&words).nth(2));
+    }
+
+    #[test]
+    fn test_checked_iter_too_many_entries_invalidformat() {
+        let words = vec![AT_HWCAP; 2 * MAX_STACK_AUXV_ENTRIES + 2];
+        let results: Vec<_> = checked_iter(&words).collect();
+        assert_eq!(MAX_STACK_AUXV_ENTRIES + 1, results.len());
+        assert_eq!(Err(StackAuxvError::InvalidFormat), results[MAX_STACK_AUXV_ENTRIES]);
+    }
+
+    #[test]
+    fn test_initial_stack_parses_args_env_and_auxv() {
+        let (arg0, arg1, env0) = (b"prog\0", b"--flag\0", b"HOME=/\0");
+        let stack: Vec<usize> = vec![
+            2, arg0.as_ptr() as usize, arg1.as_ptr() as usize, 0,
+            env0.as_ptr() as usize, 0,
+            AT_HWCAP as usize, 0xbfeb_fbff, 0, 0,
+        ];
+
+        let initial = unsafe { InitialStack::from_stack_pointer(stack.as_ptr()) };
+        assert_eq!(2, initial.argc());
+        assert_eq!(vec!["prog", "--flag"],
+                   unsafe { initial.args() }.map(|s| s.to_str().unwrap()).collect::<Vec<_>>());
+        assert_eq!(vec!["HOME=/"],
+                   unsafe { initial.env() }.map(|s| s.to_str().unwrap()).collect::<Vec<_>>());
+        assert!(unsafe { (*initial.argv().add(2)).is_null() });
+        assert_eq!(env0.as_ptr(), unsafe { *initial.envp() } as *const u8);
+        assert_eq!(vec![AuxvPair { key: AT_HWCAP, value: 0xbfeb_fbff as AuxvType }],
+                   initial.auxv().collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_initial_stack_within_bounds() {
+        let env0 = b"HOME=/\0";
+        let mut stack: Vec<usize> = vec![
+            1, env0.as_ptr() as usize, 0,
+            env0.as_ptr() as usize, 0,
+            AT_HWCAP as usize, 0xbfeb_fbff, 0, 0,
+        ];
+        let bounds = |stack: &[usize]| {
+            let start = stack.as_ptr() as usize;
+            (start, start + std::mem::size_of_val(stack))
+        };
+
+        let word = std::mem::size_of::<usize>();
+        let (start, end) = bounds(&stack);
+        let initial = super::initial_stack_within(start, start, end).unwrap();
+        assert_eq!(1, initial.argc());
+        assert_eq!(vec!["HOME=/"],
+                   unsafe { initial.env() }.map(|s| s.to_str().unwrap()).collect::<Vec<_>>());
+
+        // auxv runs off the end without an AT_NULL
+        assert_eq!(StackAuxvError::InvalidFormat,
+                   super::initial_stack_within(start, start, end - 2 * word).unwrap_err());
+        // the environment array runs off the end
+        assert_eq!(StackAuxvError::NotOnStack,
+                   super::initial_stack_within(start, start, start + 4 * word).unwrap_err());
+
+        // argc claims more arguments than there's room for
+        stack[0] = usize::MAX / 4;
+        let (start, end) = bounds(&stack);
+        assert_eq!(StackAuxvError::NotOnStack,
+                   super::initial_stack_within(start, start, end).unwrap_err());
+    }
 }
@@ -0,0 +1,11 @@
+//! Helpers shared by the unit tests.
+
+use std::fs::File;
+use std::io::Read;
+
+/// Read a file from `src/test-data`.
+pub fn read_fixture(name: &str) -> Vec<u8> {
+    let mut buf = Vec::new();
+    File::open(format!("src/test-data/{}", name)).unwrap().read_to_end(&mut buf).unwrap();
+    buf
+}
@@ -0,0 +1,637 @@
+//! Look up symbols in the vDSO located via `AT_SYSINFO_EHDR`.
+//!
+//! The kernel maps a small shared object, the vDSO, into every process and puts the address of
+//! its ELF header in auxv as `AT_SYSINFO_EHDR`. It exports fast, syscall-free implementations of
+//! things like `clock_gettime`. Normally libc finds and uses these for you, but if you don't have
+//! a libc (or don't want to go through it), you need to do the dynamic linker's job yourself:
+//! walk the dynamic section, then use the symbol hash tables and version definitions to find
+//! e.g. `__vdso_clock_gettime` at version `LINUX_2.6`.
+//!
+//! `Vdso::parse` does that over a byte slice, so it can be used on a vDSO image from anywhere
+//! (a file, a buffer read from another process). `current_vdso` does it for the current process.
+//! This follows the same rules as `tools/testing/selftests/vDSO/parse_vdso.c` in the Linux
+//! source: only defined global or weak symbols of type `STT_FUNC` or `STT_NOTYPE` are considered.
+//!
+//! Symbol names and versions differ between architectures; see `vdso(7)`.
+//!
+//! The vDSO also carries a couple of ELF notes: the kernel's `LINUX_VERSION_CODE`, which is a
+//! cheap way to find the running kernel version without `uname` (handy in a seccomp sandbox),
+//! and usually a GNU build ID. See `Vdso::kernel_version` and `Vdso::build_id`.
+//!
+//! To look at the vDSO with other tools (e.g. to disassemble it), `dump_process_vdso` copies
+//! the vDSO out of any process's memory, given its `AT_SYSINFO_EHDR` from whatever source, and
+//! writes it to a file. The vDSO is a complete shared object, section headers and all, so the
+//! result is a regular ELF file that `objdump` and friends are happy with. `read_procfs_vdso` does
+//! the copying for a process in any `Procfs`.
+
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{Read, Seek, SeekFrom, Write};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+#[cfg(feature = "std")]
+use super::{AuxvType, AT_SYSINFO_EHDR};
+#[cfg(feature = "std")]
+use super::procfs::{Procfs, Target};
+use super::elf::{self, ElfImage, ElfError, DT_GNU_HASH, DT_HASH, DT_STRTAB, DT_SYMTAB, DT_VERDEF,
+                 DT_VERSYM, EHDR_SIZE, NT_GNU_BUILD_ID, PT_LOAD};
+
+/// Errors from finding or parsing a vDSO.
+#[derive(Debug, PartialEq)]
+pub enum VdsoError {
+    /// there is no `AT_SYSINFO_EHDR` entry in auxv, so there is no vDSO
+    NotAvailable,
+    /// the vDSO image is invalid or unsupported
+    InvalidFormat,
+    /// the address isn't in any of the process's mappings
+    NotMapped,
+    /// an io error was encountered
+    IoError
+}
+
+/// A symbol exported by the vDSO.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VdsoSymbol<'a> {
+    /// The symbol name, e.g. `__vdso_clock_gettime`
+    pub name: &'a str,
+    /// The name of the symbol's version definition, e.g. `LINUX_2.6`, if it has one
+    pub version: Option<&'a str>,
+    /// The address of the symbol at runtime
+    pub address: usize,
+    /// The size of the symbol
+    pub size: usize,
+}
+
+/// A kernel version as encoded in `LINUX_VERSION_CODE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KernelVersion {
+    pub major: u32,
+    pub minor: u32,
+    /// The patch level (aka sublevel). `LINUX_VERSION_CODE` only has 8 bits for this, so kernels
+    /// with a sublevel of 255 or more report 255.
+    pub patch: u32,
+}
+
+/// A parsed vDSO image.
+#[derive(Debug, Clone, Copy)]
+pub struct Vdso<'a> {
+    image: ElfImage<'a>,
+    /// runtime address minus link-time address
+    load_offset: usize,
+    // offsets of the various tables in the image
+    symtab: usize,
+    strtab: usize,
+    hash: Option<usize>,
+    gnu_hash: Option<usize>,
+    versym: Option<usize>,
+    verdef: Option<usize>,
+    symbol_count: usize,
+}
+
+#[cfg(target_pointer_width="32")]
+const SYM_SIZE: usize = 16;
+#[cfg(target_pointer_width="64")]
+const SYM_SIZE: usize = 24;
+/// Elf_Verdef is the same size in both classes
+const VERDEF_SIZE: usize = 20;
+
+const STT_NOTYPE: u8 = 0;
+const STT_FUNC: u8 = 2;
+const STB_GLOBAL: u8 = 1;
+const STB_WEAK: u8 = 2;
+const SHN_UNDEF: u16 = 0;
+const VER_FLG_BASE: u16 = 1;
+
+/// Find and parse the vDSO of the current process.
+#[cfg(feature = "std")]
+pub fn current_vdso() -> Result<Vdso<'static>, VdsoError> {
+    match super::current_auxv_value(AT_SYSINFO_EHDR) {
+        Some(addr) if addr != 0 => unsafe { vdso_at(addr as usize) },
+        _ => Err(VdsoError::NotAvailable)
+    }
+}
+
+/// Parse the vDSO whose ELF header is at `addr` in the current process.
+///
+/// # Safety
+///
+/// `addr` must be the address of a complete ELF image mapped in this process that stays mapped
+/// for the rest of the process's life, like the value of `AT_SYSINFO_EHDR`.
+pub unsafe fn vdso_at(addr: usize) -> Result<Vdso<'static>, VdsoError> {
+    let image = image_at(addr).map_err(|_| VdsoError::InvalidFormat)?;
+    Vdso::parse(image.bytes(), addr)
+}
+
+/// Get a slice covering the entire ELF image at `addr`, using its headers to find the length.
+///
+/// # Safety
+///
+/// `addr` must be the address of a complete ELF image mapped in this process.
+pub(crate) unsafe fn image_at(addr: usize) -> Result<ElfImage<'static>, ElfError> {
+    let ptr = addr as *const u8;
+    let header = ElfImage::parse(std::slice::from_raw_parts(ptr, EHDR_SIZE))?;
+    let with_phdrs = ElfImage::parse(std::slice::from_raw_parts(ptr, header.program_headers_end()))?;
+    ElfImage::parse(std::slice::from_raw_parts(ptr, with_phdrs.extent()))
+}
+
+/// Copy the vDSO image out of the memory of process `pid`.
+///
+/// `sysinfo_ehdr` is the process's `AT_SYSINFO_EHDR` value. The size of the image comes from the
+/// mapping that contains it in `/proc/<pid>/maps`. Reading another process's memory requires
+/// the same permissions as `ptrace`.
+///
+/// The returned image is trimmed to the length its headers describe when it has the same class as
+/// the current process; otherwise (e.g. the 32-bit vDSO of a compat process) the whole mapping is
+/// returned.
+#[cfg(feature = "std")]
+pub fn read_process_vdso(pid: u32, sysinfo_ehdr: AuxvType) -> Result<Vec<u8>, VdsoError> {
+    read_procfs_vdso(&Procfs::new(), Target::Pid(pid), sysinfo_ehdr)
+}
+
+/// Copy the vDSO image out of the memory of `target`, via its `maps` and `mem` files in `procfs`.
+///
+/// See `read_process_vdso`.
+#[cfg(feature = "std")]
+pub fn read_procfs_vdso(procfs: &Procfs, target: Target, sysinfo_ehdr: AuxvType)
+                        -> Result<Vec<u8>, VdsoError> {
+    if sysinfo_ehdr == 0 {
+        return Err(VdsoError::NotAvailable);
+    }
+    // AuxvType is only u64 on 64-bit systems
+    #[allow(clippy::unnecessary_cast)]
+    let addr = sysinfo_ehdr as u64;
+
+    let mappings = procfs.read_maps(target).map_err(|_| VdsoError::IoError)?;
+    let mapping = mappings.iter()
+        .find(|m| m.contains(addr))
+        .ok_or(VdsoError::NotMapped)?;
+
+    let mut image = vec![0; (mapping.end - addr) as usize];
+    let mut mem = File::open(procfs.path(target, "mem")).map_err(|_| VdsoError::IoError)?;
+    mem.seek(SeekFrom::Start(addr)).map_err(|_| VdsoError::IoError)?;
+    mem.read_exact(&mut image).map_err(|_| VdsoError::IoError)?;
+
+    match ElfImage::parse(&image) {
+        Ok(parsed) => {
+            Vdso::parse(&image, addr as usize)?;
+            let extent = parsed.extent();
+            if extent > image.len() {
+                return Err(VdsoError::InvalidFormat);
+            }
+            image.truncate(extent);
+        }
+        Err(ElfError::UnsupportedClass) => {}
+        Err(ElfError::InvalidFormat) => return Err(VdsoError::InvalidFormat)
+    }
+
+    Ok(image)
+}
+
+/// Copy the vDSO image of process `pid` to a new ELF file at `path`.
+///
+/// See `read_process_vdso`.
+#[cfg(feature = "std")]
+pub fn dump_process_vdso(pid: u32, sysinfo_ehdr: AuxvType, path: &Path) -> Result<(), VdsoError> {
+    let image = read_process_vdso(pid, sysinfo_ehdr)?;
+
+    File::create(path)
+        .and_then(|mut f| f.write_all(&image))
+        .map_err(|_| VdsoError::IoError)
+}
+
+impl<'a> Vdso<'a> {
+    /// Parse a vDSO image that is (or was) mapped at `base`.
+    ///
+    /// `base` is only used to compute symbol addresses, so if you just want to inspect an image
+    /// that isn't mapped anywhere, 0 is fine and addresses will be relative to the image.
+    pub fn parse(image: &'a [u8], base: usize) -> Result<Vdso<'a>, VdsoError> {
+        let image = ElfImage::parse(image).map_err(|_| VdsoError::InvalidFormat)?;
+
+        // the vDSO has exactly one PT_LOAD, and it covers the whole image
+        let load = image.program_headers()
+            .find(|p| p.p_type == PT_LOAD)
+            .ok_or(VdsoError::InvalidFormat)?;
+        let load_offset = base.wrapping_add(load.p_offset as usize).wrapping_sub(load.p_vaddr as usize);
+
+        let mut symtab = None;
+        let mut strtab = None;
+        let mut hash = None;
+        let mut gnu_hash = None;
+        let mut versym = None;
+        let mut verdef = None;
+        for (tag, value) in image.dynamic_entries() {
+            let slot = match tag {
+                DT_SYMTAB => &mut symtab,
+                DT_STRTAB => &mut strtab,
+                DT_HASH => &mut hash,
+                DT_GNU_HASH => &mut gnu_hash,
+                DT_VERSYM => &mut versym,
+                DT_VERDEF => &mut verdef,
+                _ => continue
+            };
+            *slot = Some(image.vaddr_to_offset(value).ok_or(VdsoError::InvalidFormat)?);
+        }
+
+        let symtab = symtab.ok_or(VdsoError::InvalidFormat)?;
+        let strtab = strtab.ok_or(VdsoError::InvalidFormat)?;
+        // versions are only meaningful when both tables are present
+        if versym.is_none() || verdef.is_none() {
+            versym = None;
+            verdef = None;
+        }
+
+        let mut vdso = Vdso {
+            image,
+            load_offset,
+            symtab,
+            strtab,
+            hash,
+            gnu_hash,
+            versym,
+            verdef,
+            symbol_count: 0,
+        };
+        let symbol_count = match (vdso.hash, vdso.gnu_hash) {
+            (Some(h), _) => h.checked_add(4)
+                .and_then(|nchain| elf::read_u32(image.bytes(), nchain))
+                .ok_or(VdsoError::InvalidFormat)? as usize,
+            (None, Some(_)) => vdso.gnu_hash_symbol_count().ok_or(VdsoError::InvalidFormat)?,
+            (None, None) => return Err(VdsoError::InvalidFormat)
+        };
+        // don't trust the hash table's count past the end of the image
+        let symtab_len = image.bytes().len().checked_sub(symtab).ok_or(VdsoError::InvalidFormat)?;
+        vdso.symbol_count = std::cmp::min(symbol_count, symtab_len / SYM_SIZE);
+
+        Ok(vdso)
+    }
+
+    /// The underlying ELF image.
+    pub fn image(&self) -> &ElfImage<'a> {
+        &self.image
+    }
+
+    /// The version of the kernel that provided this vDSO, from its `Linux` note.
+    pub fn kernel_version(&self) -> Option<KernelVersion> {
+        self.image.notes()
+            .find(|n| n.name == b"Linux" && n.note_type == 0)
+            .and_then(|n| elf::read_u32(n.desc, 0))
+            .map(|code| KernelVersion {
+                major: code >> 16,
+                minor: (code >> 8) & 0xff,
+                patch: code & 0xff,
+            })
+    }
+
+    /// The vDSO's GNU build ID, if it has one.
+    pub fn build_id(&self) -> Option<&'a [u8]> {
+        self.image.notes()
+            .find(|n| n.name == b"GNU" && n.note_type == NT_GNU_BUILD_ID)
+            .map(|n| n.desc)
+    }
+
+    /// Iterate over the exported symbols.
+    pub fn symbols(&self) -> VdsoSymbolIter<'a> {
+        VdsoSymbolIter {
+            vdso: *self,
+            index: 1,
+        }
+    }
+
+    /// Look up a symbol by name and version, using the image's hash table.
+    ///
+    /// If the vDSO has no version information, any symbol with the right name matches.
+    pub fn lookup(&self, name: &str, version: &str) -> Option<VdsoSymbol<'a>> {
+        let matches = |index: usize| {
+            self.symbol(index)
+                .filter(|s| s.name == name && s.version.is_none_or(|v| v == version))
+        };
+
+        if let Some(gnu_hash) = self.gnu_hash {
+            return self.gnu_hash_lookup(gnu_hash, name, matches);
+        }
+
+        let hash = self.hash?;
+        let bytes = self.image.bytes();
+        let nbucket = elf::read_u32(bytes, hash)? as usize;
+        if nbucket == 0 {
+            return None;
+        }
+        let bucket = (elf_hash(name.as_bytes()) as usize) % nbucket;
+        let buckets = hash.checked_add(8)?;
+        let chain = word_offset(buckets, nbucket)?;
+
+        let mut index = elf::read_u32(bytes, word_offset(buckets, bucket)?)? as usize;
+        // bound the walk in case the chain has a cycle
+        for _ in 0..self.symbol_count {
+            if index == 0 {
+                break;
+            }
+            if let Some(s) = matches(index) {
+                return Some(s);
+            }
+            index = elf::read_u32(bytes, word_offset(chain, index)?)? as usize;
+        }
+
+        None
+    }
+
+    fn gnu_hash_lookup<F>(&self, gnu_hash: usize, name: &str, matches: F) -> Option<VdsoSymbol<'a>>
+        where F: Fn(usize) -> Option<VdsoSymbol<'a>> {
+        let bytes = self.image.bytes();
+        let (nbuckets, symoffset, buckets) = self.gnu_hash_layout(gnu_hash)?;
+        if nbuckets == 0 {
+            return None;
+        }
+        let chains = word_offset(buckets, nbuckets)?;
+        let h = gnu_hash_of(name.as_bytes());
+        let bucket = h as usize % nbuckets;
+
+        let mut index = elf::read_u32(bytes, word_offset(buckets, bucket)?)? as usize;
+        if index < symoffset {
+            return None;
+        }
+        // a chain can't be longer than the symbol table
+        while index < self.symbol_count {
+            let chain_hash = elf::read_u32(bytes, word_offset(chains, index - symoffset)?)?;
+            if chain_hash | 1 == h | 1 {
+                if let Some(s) = matches(index) {
+                    return Some(s);
+                }
+            }
+            // the low bit marks the end of a chain
+            if chain_hash & 1 != 0 {
+                return None;
+            }
+            index += 1;
+        }
+
+        None
+    }
+
+    /// Returns `(nbuckets, symoffset, offset of buckets)`.
+    fn gnu_hash_layout(&self, gnu_hash: usize) -> Option<(usize, usize, usize)> {
+        let bytes = self.image.bytes();
+        let nbuckets = elf::read_u32(bytes, gnu_hash)? as usize;
+        let symoffset = elf::read_u32(bytes, gnu_hash.checked_add(4)?)? as usize;
+        let bloom_size = elf::read_u32(bytes, gnu_hash.checked_add(8)?)? as usize;
+        let buckets = bloom_size.checked_mul(std::mem::size_of::<usize>())
+            .and_then(|bloom| gnu_hash.checked_add(16)?.checked_add(bloom))?;
+        Some((nbuckets, symoffset, buckets))
+    }
+
+    /// GNU hash tables don't record how many symbols there are, so find the end of the last
+    /// chain.
+    fn gnu_hash_symbol_count(&self) -> Option<usize> {
+        let gnu_hash = self.gnu_hash?;
+        let bytes = self.image.bytes();
+        let (nbuckets, symoffset, buckets) = self.gnu_hash_layout(gnu_hash)?;
+        let chains = word_offset(buckets, nbuckets)?;
+
+        let mut last = 0;
+        for i in 0..nbuckets {
+            last = std::cmp::max(last, elf::read_u32(bytes, word_offset(buckets, i)?)? as usize);
+        }
+        if last < symoffset {
+            return Some(symoffset);
+        }
+        while elf::read_u32(bytes, word_offset(chains, last - symoffset)?)? & 1 == 0 {
+            last += 1;
+        }
+        Some(last + 1)
+    }
+
+    /// The symbol at `index` in the symbol table, if it's one we consider exported.
+    fn symbol(&self, index: usize) -> Option<VdsoSymbol<'a>> {
+        let start = index.checked_mul(SYM_SIZE).and_then(|i| self.symtab.checked_add(i))?;
+        // the fields are read from the symbol's own slice, so their offsets can't overflow
+        let sym = self.image.bytes().get(start..start.checked_add(SYM_SIZE)?)?;
+        let word = std::mem::size_of::<usize>();
+
+        let name = elf::read_u32(sym, 0)? as usize;
+        let (value, size, info, shndx) = if word == 4 {
+            (elf::read_word(sym, 4)?, elf::read_word(sym, 8)?, sym[12], elf::read_u16(sym, 14)?)
+        } else {
+            (elf::read_word(sym, 8)?, elf::read_word(sym, 16)?, sym[4], elf::read_u16(sym, 6)?)
+        };
+
+        let sym_type = info & 0xf;
+        let sym_bind = info >> 4;
+        if (sym_type != STT_FUNC && sym_type != STT_NOTYPE)
+            || (sym_bind != STB_GLOBAL && sym_bind != STB_WEAK)
+            || shndx == SHN_UNDEF {
+            return None;
+        }
+
+        Some(VdsoSymbol {
+            name: self.string(name)?,
+            version: self.version_name(index),
+            address: self.load_offset.wrapping_add(value),
+            size,
+        })
+    }
+
+    /// The name of the version definition for the symbol at `index`.
+    fn version_name(&self, index: usize) -> Option<&'a str> {
+        let bytes = self.image.bytes();
+        let versym = index.checked_mul(2).and_then(|i| self.versym?.checked_add(i))?;
+        let ver = elf::read_u16(bytes, versym)? & 0x7fff;
+
+        let mut def = self.verdef?;
+        // bound the walk in case the list has a cycle
+        for _ in 0..self.symbol_count + 1 {
+            // Elf_Verdef: vd_version, vd_flags, vd_ndx, vd_cnt (u16), vd_hash, vd_aux, vd_next (u32)
+            let verdef = bytes.get(def..def.checked_add(VERDEF_SIZE)?)?;
+            let flags = elf::read_u16(verdef, 2)?;
+            let ndx = elf::read_u16(verdef, 4)?;
+            if flags & VER_FLG_BASE == 0 && ndx & 0x7fff == ver {
+                let aux = def.checked_add(elf::read_u32(verdef, 12)? as usize)?;
+                // Elf_Verdaux: vda_name, vda_next (u32)
+                return self.string(elf::read_u32(bytes, aux)? as usize);
+            }
+            let next = elf::read_u32(verdef, 16)? as usize;
+            if next == 0 {
+                return None;
+            }
+            def = def.checked_add(next)?;
+        }
+
+        None
+    }
+
+    fn string(&self, offset: usize) -> Option<&'a str> {
+        elf::read_cstr(self.image.bytes(), self.strtab.checked_add(offset)?)
+            .and_then(|s| std::str::from_utf8(s).ok())
+    }
+}
+
+/// An iterator over the symbols exported by a vDSO.
+pub struct VdsoSymbolIter<'a> {
+    vdso: Vdso<'a>,
+    index: usize,
+}
+
+impl<'a> Iterator for VdsoSymbolIter<'a> {
+    type Item = VdsoSymbol<'a>;
+    fn next(&mut self) -> Option<VdsoSymbol<'a>> {
+        while self.index < self.vdso.symbol_count {
+            let index = self.index;
+            self.index += 1;
+            if let Some(s) = self.vdso.symbol(index) {
+                return Some(s);
+            }
+        }
+        None
+    }
+}
+
+/// The offset of the `index`th 32-bit word of the table at `table`, unless it overflows.
+fn word_offset(table: usize, index: usize) -> Option<usize> {
+    index.checked_mul(4).and_then(|i| table.checked_add(i))
+}
+
+/// The SysV ELF hash function.
+fn elf_hash(name: &[u8]) -> u32 {
+    let mut h: u32 = 0;
+    for &c in name {
+        h = (h << 4).wrapping_add(u32::from(c));
+        let g = h & 0xf000_0000;
+        if g != 0 {
+            h ^= g >> 24;
+        }
+        h &= !g;
+    }
+    h
+}
+
+/// The GNU hash function (DJB's hash).
+fn gnu_hash_of(name: &[u8]) -> u32 {
+    name.iter().fold(5381u32, |h, &c| h.wrapping_mul(33).wrapping_add(u32::from(c)))
+}
+
+#[cfg(all(test, target_pointer_width="64"))]
+mod tests {
+    use super::super::elf::{ElfError, ElfImage};
+    use super::super::test_support::read_fixture;
+    use super::{KernelVersion, Vdso, VdsoSymbol, SYM_SIZE};
+
+    #[test]
+    fn test_lookup_linux_x64_vdso() {
+        let image = read_fixture("linux-x64-vdso.so");
+        let vdso = Vdso::parse(&image, 0x7f00_0000_0000).unwrap();
+
+        assert_eq!(Some(VdsoSymbol {
+            name: "__vdso_clock_gettime",
+            version: Some("LINUX_2.6"),
+            address: 0x7f00_0000_0ec0,
+            size: 5
+        }), vdso.lookup("__vdso_clock_gettime", "LINUX_2.6"));
+        assert_eq!(0x7f00_0000_0e90, vdso.lookup("time", "LINUX_2.6").unwrap().address);
+
+        assert_eq!(None, vdso.lookup("__vdso_clock_gettime", "LINUX_2.5"));
+        assert_eq!(None, vdso.lookup("__vdso_nonexistent", "LINUX_2.6"));
+    }
+
+    #[test]
+    fn test_symbols_linux_x64_vdso() {
+        let image = read_fixture("linux-x64-vdso.so");
+        let vdso = Vdso::parse(&image, 0).unwrap();
+
+        let mut names: Vec<&str> = vdso.symbols().map(|s| s.name).collect();
+        names.sort();
+        assert_eq!(vec!["__vdso_clock_getres", "__vdso_clock_gettime", "__vdso_getcpu",
+                        "__vdso_getrandom", "__vdso_gettimeofday", "__vdso_time", "clock_getres",
+                        "clock_gettime", "getcpu", "getrandom", "gettimeofday", "time"], names);
+        // every one of them should be reachable through the hash table
+        for s in vdso.symbols() {
+            assert_eq!(Some(s), vdso.lookup(s.name, "LINUX_2.6"));
+        }
+    }
+
+    #[test]
+    fn test_lookup_linux_x64_vdso_sysv_hash() {
+        let image = read_fixture("linux-x64-vdso.so");
+        let mut vdso = Vdso::parse(&image, 0).unwrap();
+        // this image has both tables; make sure the fallback works too
+        vdso.gnu_hash = None;
+
+        assert_eq!(0xec0, vdso.lookup("__vdso_clock_gettime", "LINUX_2.6").unwrap().address);
+        assert_eq!(None, vdso.lookup("__vdso_clock_gettime", "LINUX_2.5"));
+        assert_eq!(12, vdso.symbols().count());
+    }
+
+    #[test]
+    fn test_notes_linux_x64_vdso() {
+        let image = read_fixture("linux-x64-vdso.so");
+        let vdso = Vdso::parse(&image, 0).unwrap();
+
+        assert_eq!(Some(KernelVersion { major: 6, minor: 18, patch: 44 }), vdso.kernel_version());
+        assert_eq!(Some(&[0x0a, 0xc2, 0x51, 0x57, 0xdd, 0x9a, 0x70, 0x5e, 0xea, 0x8c, 0x6b, 0x83,
+                          0xc4, 0xe5, 0x0b, 0xb8, 0x29, 0x4c, 0x13, 0x24][..]),
+                   vdso.build_id());
+    }
+
+    #[test]
+    fn test_parse_truncated_vdso_invalidformat() {
+        let image = read_fixture("linux-x64-vdso.so");
+
+        assert!(Vdso::parse(&image[..200], 0).is_err());
+    }
+
+    #[test]
+    fn test_parse_huge_nchain_bounded_by_symtab() {
+        let mut image = read_fixture("linux-x64-vdso.so");
+        let (hash, symtab) = {
+            let vdso = Vdso::parse(&image, 0).unwrap();
+            (vdso.hash.unwrap(), vdso.symtab)
+        };
+        // nchain, which is where the symbol count comes from
+        image[hash + 4..hash + 8].copy_from_slice(&u32::MAX.to_ne_bytes());
+        let vdso = Vdso::parse(&image, 0).unwrap();
+
+        assert_eq!((image.len() - symtab) / SYM_SIZE, vdso.symbol_count);
+        assert_eq!(0xec0, vdso.lookup("__vdso_clock_gettime", "LINUX_2.6").unwrap().address);
+        assert!(vdso.symbols().count() >= 12);
+    }
+
+    #[test]
+    fn test_offsets_past_address_space_end_not_found() {
+        let image = read_fixture("linux-x64-vdso.so");
+        let vdso = Vdso::parse(&image, 0).unwrap();
+        // a malformed image can put tables anywhere; adding to these offsets must not overflow
+        let near_end = usize::MAX - 8;
+
+        let mut bad_strtab = vdso;
+        bad_strtab.strtab = near_end;
+        assert_eq!(0, bad_strtab.symbols().count());
+
+        let mut bad_symtab = vdso;
+        bad_symtab.symtab = near_end;
+        assert_eq!(None, bad_symtab.lookup("__vdso_clock_gettime", "LINUX_2.6"));
+
+        let mut bad_versym = vdso;
+        bad_versym.versym = Some(near_end);
+        assert!(bad_versym.symbols().all(|s| s.version.is_none()));
+
+        let mut bad_verdef = vdso;
+        bad_verdef.verdef = Some(near_end);
+        assert!(bad_verdef.symbols().all(|s| s.version.is_none()));
+
+        let mut bad_hash = vdso;
+        bad_hash.gnu_hash = Some(near_end);
+        assert_eq!(None, bad_hash.lookup("__vdso_clock_gettime", "LINUX_2.6"));
+        bad_hash.gnu_hash = None;
+        bad_hash.hash = Some(near_end);
+        assert_eq!(None, bad_hash.lookup("__vdso_clock_gettime", "LINUX_2.6"));
+    }
+
+    #[test]
+    fn test_parse_overflowing_program_headers_invalidformat() {
+        let mut image = read_fixture("linux-x64-vdso.so");
+        // e_phoff, so close to the top of the address space that the table would wrap
+        image[32..40].copy_from_slice(&(usize::MAX - 8).to_ne_bytes());
+
+        assert_eq!(Err(ElfError::InvalidFormat), ElfImage::parse(&image).map(|_| ()));
+    }
+}
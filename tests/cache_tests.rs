@@ -0,0 +1,42 @@
+#[cfg(target_os="linux")]
+extern crate auxv;
+
+#[cfg(target_os="linux")]
+use std::thread;
+
+#[cfg(target_os="linux")]
+use auxv::cache::{cached_auxv, cached_auxv_value, AuxvSource};
+#[cfg(target_os="linux")]
+use auxv::procfs::iterate_procfs_auxv;
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_cached_auxv_matches_procfs() {
+    let table = cached_auxv();
+    assert!(table.source().is_some());
+    if !cfg!(feature = "init-array") {
+        assert_eq!(Some(AuxvSource::Procfs), table.source());
+    }
+
+    let pairs = iterate_procfs_auxv(std::process::id()).unwrap()
+        .collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(pairs.len(), table.len());
+    for pair in pairs {
+        assert_eq!(Some(pair.value), cached_auxv_value(pair.key));
+    }
+    assert_eq!(None, cached_auxv_value(0xfff));
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_cached_auxv_shared_between_threads() {
+    let here = cached_auxv() as *const _ as usize;
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| thread::spawn(|| (cached_auxv() as *const _ as usize, cached_auxv_value(6))))
+        .collect();
+
+    for handle in handles {
+        assert_eq!((here, cached_auxv_value(6)), handle.join().unwrap());
+    }
+}
@@ -0,0 +1,31 @@
+//! Helpers shared by the integration tests.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A directory in the system temp dir that's removed when dropped, so a failed test doesn't leave
+/// it behind.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Create an empty directory named after `name` and the current pid, replacing whatever an
+    /// earlier run left there.
+    pub fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("auxv-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
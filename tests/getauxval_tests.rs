@@ -28,7 +28,7 @@ fn test_getauxv_hwcap_linux_finds_uid_matches_libc() {
     let uid = result.unwrap();
 
     let libc_uid = unsafe { libc::getuid() };
-    assert_eq!(libc_uid as u64, uid as u64);
+    assert_eq!(libc_uid as auxv::AuxvType, uid);
 }
 
 #[test]
@@ -39,3 +39,43 @@ fn test_getauxv_hwcap_linux_doesnt_find_bogus_key() {
     // AT_NULL aka 0 is effectively the EOF for auxv, so it's never a valid key
     assert_eq!(GetauxvalError::NotFound, native_getauxval.getauxval(0).unwrap_err());
 }
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_dlsym_getauxval_matches_native() {
+    use auxv::getauxval::DlsymGetauxval;
+
+    let native_getauxval = NativeGetauxval {};
+    let dlsym_getauxval = DlsymGetauxval {};
+
+    // AT_NULL, AT_PAGESZ, AT_UID, AT_HWCAP, and something that's never set
+    for &key in &[0, 6, 11, AT_HWCAP, 0xfff] {
+        assert_eq!(native_getauxval.getauxval(key), dlsym_getauxval.getauxval(key));
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_stale_enoent_does_not_hide_found_key() {
+    use auxv::getauxval::DlsymGetauxval;
+
+    let native_getauxval = NativeGetauxval {};
+    let dlsym_getauxval = DlsymGetauxval {};
+    let expected = native_getauxval.getauxval(6).unwrap();
+
+    for getauxval in &[&native_getauxval as &dyn Getauxval, &dlsym_getauxval] {
+        // left over from some earlier failed libc call
+        unsafe { *libc::__errno_location() = libc::ENOENT };
+
+        assert_eq!(Ok(expected), getauxval.getauxval(6));
+        // and errno is left alone
+        assert_eq!(libc::ENOENT, unsafe { *libc::__errno_location() });
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_native_getauxval_zero_value_is_found() {
+    // AT_SECURE is present, and 0 unless setuid or similar
+    assert_eq!(Ok(0), NativeGetauxval {}.getauxval(23));
+}
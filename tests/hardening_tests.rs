@@ -0,0 +1,24 @@
+#[cfg(target_os = "linux")]
+extern crate auxv;
+
+#[test]
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn current_report_matches_exe_on_disk() {
+    use std::io::Read;
+    use auxv::elf::ElfImage;
+    use auxv::hardening::{GnuProperties, HardeningReport};
+
+    let report = HardeningReport::current().unwrap();
+
+    let mut exe = Vec::new();
+    std::fs::File::open("/proc/self/exe").unwrap().read_to_end(&mut exe).unwrap();
+    let image = ElfImage::parse(&exe).unwrap();
+    let properties = GnuProperties::from_notes(image.notes());
+
+    let x86 = properties.x86_feature_1_and.unwrap_or(0);
+    let aarch64 = properties.aarch64_feature_1_and.unwrap_or(0);
+    assert_eq!(x86 & auxv::hardening::GNU_PROPERTY_X86_FEATURE_1_IBT != 0, report.ibt.marked);
+    assert_eq!(x86 & auxv::hardening::GNU_PROPERTY_X86_FEATURE_1_SHSTK != 0, report.shstk.marked);
+    assert_eq!(aarch64 & auxv::hardening::GNU_PROPERTY_AARCH64_FEATURE_1_BTI != 0, report.bti.marked);
+    assert_eq!(aarch64 & auxv::hardening::GNU_PROPERTY_AARCH64_FEATURE_1_PAC != 0, report.pac.marked);
+}
@@ -0,0 +1,40 @@
+#[cfg(target_os = "linux")]
+extern crate auxv;
+
+#[cfg(target_os = "linux")]
+use auxv::layout::ExecutableLayout;
+
+#[test]
+#[cfg(target_os = "linux")]
+fn current_layout_contains_entry_and_this_function() {
+    let layout = ExecutableLayout::current().unwrap();
+
+    assert!(layout.contains(layout.entry));
+    let here = current_layout_contains_entry_and_this_function as *const () as usize;
+    assert!(layout.contains(here));
+    assert_eq!(here, layout.to_runtime_address(layout.to_file_address(here)));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn current_layout_pie_matches_bias() {
+    let layout = ExecutableLayout::current().unwrap();
+
+    // a PIE can't be loaded at 0, and a non-PIE is always loaded where it was linked
+    assert_eq!(layout.is_pie, layout.load_bias != 0);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn current_build_id_matches_exe_on_disk() {
+    use std::io::Read;
+
+    let mut exe = Vec::new();
+    std::fs::File::open("/proc/self/exe").unwrap().read_to_end(&mut exe).unwrap();
+    let image = auxv::elf::ElfImage::parse(&exe).unwrap();
+    let on_disk = image.notes()
+        .find(|n| n.name == b"GNU" && n.note_type == auxv::elf::NT_GNU_BUILD_ID)
+        .map(|n| n.desc);
+
+    assert_eq!(on_disk, auxv::layout::current_build_id());
+}
@@ -0,0 +1,36 @@
+#[cfg(target_os = "linux")]
+extern crate auxv;
+
+#[test]
+#[cfg(target_os = "linux")]
+fn loaded_objects_starts_with_executable() {
+    let objects = unsafe { auxv::linkmap::loaded_objects().unwrap() };
+    let layout = auxv::layout::ExecutableLayout::current().unwrap();
+
+    assert_eq!(layout.load_bias, objects[0].base);
+    let mut bases: Vec<usize> = objects.iter().map(|o| o.base).collect();
+    bases.sort();
+    bases.dedup();
+    assert_eq!(objects.len(), bases.len());
+}
+
+#[test]
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn loaded_objects_names_match_maps() {
+    let objects = unsafe { auxv::linkmap::loaded_objects().unwrap() };
+    let maps = auxv::procfs::read_procfs_maps(std::process::id()).unwrap();
+
+    // a dynamically linked test binary has at least libc and the dynamic linker
+    assert!(objects.len() >= 3);
+    for object in &objects[1..] {
+        let name = std::str::from_utf8(object.name).unwrap();
+        if !name.starts_with('/') {
+            // the vDSO, which isn't a file
+            continue;
+        }
+        let path = std::fs::canonicalize(name).unwrap();
+        assert!(maps.iter().any(|m| {
+            std::path::Path::new(&m.path) == path && m.start - m.offset == object.base as u64
+        }), "{} not mapped at {:x}", name, object.base);
+    }
+}
@@ -0,0 +1,109 @@
+// These modify the environment, which breaks iterate_stack_auxv, so they live in their own test
+// binary away from the other stack tests. Changing the environment while another thread reads it
+// is a data race, so each one also runs in a process of its own.
+
+#[cfg(target_os="linux")]
+extern crate auxv;
+
+/// Set in the copy of this binary that `alone` runs a test in.
+#[cfg(target_os="linux")]
+const CHILD_VAR: &str = "AUXV_MODIFIED_ENVIRON_CHILD";
+
+/// Run the test `name` by itself in a copy of this binary. Returns true in that copy, where the
+/// test should go ahead, and false in the original once the copy has passed.
+#[cfg(target_os="linux")]
+fn alone(name: &str) -> bool {
+    if std::env::var_os(CHILD_VAR).is_some() {
+        return true;
+    }
+
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([name, "--exact", "--test-threads=1"])
+        .env(CHILD_VAR, "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{} failed in its own process:\n{}", name,
+            String::from_utf8_lossy(&output.stdout));
+    false
+}
+
+#[test]
+#[cfg(all(target_os="linux", feature="init-array"))]
+fn startup_auxv_survives_modified_environ() {
+    if !alone("startup_auxv_survives_modified_environ") {
+        return;
+    }
+
+    use auxv::AuxvPair;
+
+    let procfs: Vec<AuxvPair> = auxv::procfs::iterate_procfs_auxv(std::process::id()).unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+
+    // enough new variables that environ has to be reallocated
+    for i in 0..100 {
+        std::env::set_var(format!("AUXV_STARTUP_TEST_{}", i), "x");
+    }
+
+    let startup: Vec<AuxvPair> = auxv::stack::iterate_startup_auxv().unwrap().collect();
+    assert_eq!(procfs, startup);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn checked_stack_auxv_detects_modified_environ() {
+    if !alone("checked_stack_auxv_detects_modified_environ") {
+        return;
+    }
+
+    // enough new variables that environ has to be reallocated, off the stack
+    for i in 0..100 {
+        std::env::set_var(format!("AUXV_CHECKED_TEST_{}", i), "x");
+    }
+
+    assert_eq!(auxv::stack::StackAuxvError::NotOnStack,
+               auxv::stack::iterate_stack_auxv_checked().unwrap_err());
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn verified_stack_auxv_survives_modified_environ() {
+    if !alone("verified_stack_auxv_survives_modified_environ") {
+        return;
+    }
+
+    let procfs: Vec<auxv::AuxvPair> = auxv::procfs::iterate_procfs_auxv(std::process::id()).unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+
+    for i in 0..100 {
+        std::env::set_var(format!("AUXV_VERIFIED_TEST_{}", i), "x");
+    }
+
+    assert!(!auxv::stack::environ_is_original().unwrap());
+    let stack: Vec<auxv::AuxvPair> = auxv::stack::iterate_stack_auxv_verified().unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(procfs, stack);
+}
+
+#[test]
+#[cfg(all(target_os="linux", target_env="gnu", feature="init-array"))]
+fn pristine_args_unaffected_by_modified_environ() {
+    if !alone("pristine_args_unaffected_by_modified_environ") {
+        return;
+    }
+
+    for i in 0..100 {
+        std::env::set_var(format!("AUXV_PRISTINE_TEST_{}", i), "x");
+    }
+
+    let pristine = auxv::stack::pristine_args().unwrap();
+    assert_eq!(std::env::args().collect::<Vec<_>>(),
+               pristine.args.iter().map(|a| a.to_str().unwrap().to_owned()).collect::<Vec<_>>());
+    assert!(!pristine.env.iter().any(|e| e.to_bytes().starts_with(b"AUXV_PRISTINE_TEST_")));
+
+    let initial = auxv::stack::initial_stack().unwrap();
+    assert_eq!(pristine.args.len(), initial.argc());
+    assert_eq!(pristine.env.len(), unsafe { initial.env() }.count());
+}
@@ -0,0 +1,52 @@
+#[cfg(target_os = "linux")]
+extern crate auxv;
+
+#[cfg(target_os = "linux")]
+use auxv::elf::{ElfPhdr, PT_LOAD};
+#[cfg(target_os = "linux")]
+use auxv::phdr::{PhdrError, ProgramHeaders};
+#[cfg(target_os = "linux")]
+use auxv::snapshot::AuxvSnapshot;
+
+#[test]
+#[cfg(target_os = "linux")]
+fn current_program_headers_match_procfs_phnum() {
+    let map = auxv::procfs::search_procfs_auxv(&[auxv::AT_PHNUM]).unwrap();
+    let headers = ProgramHeaders::current().unwrap();
+
+    assert_eq!(*map.get(&auxv::AT_PHNUM).unwrap() as usize, headers.as_slice().len());
+    assert!(headers.iter().any(|p| p.p_type == PT_LOAD));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn current_program_headers_stack_not_executable() {
+    let headers = ProgramHeaders::current().unwrap();
+
+    // PF_X
+    assert_eq!(0, headers.gnu_stack().unwrap().p_flags & 1);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn from_raw_rejects_wrong_entry_size() {
+    let headers: [ElfPhdr; 1] = unsafe { std::mem::zeroed() };
+    let phdr = headers.as_ptr() as auxv::AuxvType;
+    let phent = std::mem::size_of::<ElfPhdr>() as auxv::AuxvType;
+
+    unsafe {
+        assert_eq!(1, ProgramHeaders::from_raw(phdr, phent, 1).unwrap().as_slice().len());
+        assert_eq!(PhdrError::InvalidFormat, ProgramHeaders::from_raw(phdr, phent + 4, 1).unwrap_err());
+        assert_eq!(PhdrError::InvalidFormat, ProgramHeaders::from_raw(phdr + 1, phent, 1).unwrap_err());
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn from_snapshot_missing_keys() {
+    let snapshot = AuxvSnapshot::new(vec![auxv::AuxvPair { key: auxv::AT_PHNUM, value: 3 }]);
+
+    unsafe {
+        assert_eq!(PhdrError::NotAvailable, ProgramHeaders::from_snapshot(&snapshot).unwrap_err());
+    }
+}
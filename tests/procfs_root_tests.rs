@@ -0,0 +1,105 @@
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+extern crate auxv;
+
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+use std::fs;
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+mod common;
+
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+use auxv::procfs::{Procfs, Target};
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+use auxv::{AuxvPair, ElfClass};
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+use common::TempDir;
+
+/// A fake `/proc` with one process, 4321, using the x86_64 fixture, and `self` pointing at it.
+/// It has two threads: 4321 has the same auxv, and 4322 has an empty one.
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+fn fake_procfs(name: &str) -> TempDir {
+    let dir = TempDir::new(&format!("fake-proc-{}", name));
+    let root = dir.path();
+
+    let pid_dir = root.join("4321");
+    fs::create_dir_all(&pid_dir).unwrap();
+    fs::copy("src/test-data/linux-x64-i7-6850k.auxv", pid_dir.join("auxv")).unwrap();
+    fs::write(pid_dir.join("maps"),
+              "00400000-00452000 r-xp 00000000 08:02 173521      /usr/bin/dbus-daemon\n\
+               7ffc2e2f0000-7ffc2e311000 rw-p 00000000 00:00 0          [stack]\n").unwrap();
+    let mut stat = String::from("4321 (fake (proc)) S 1");
+    for field in 5..=52 {
+        stat.push_str(&format!(" {}", field * 10));
+    }
+    fs::write(pid_dir.join("stat"), stat).unwrap();
+    std::os::unix::fs::symlink("4321", root.join("self")).unwrap();
+
+    let task_dir = pid_dir.join("task");
+    fs::create_dir_all(task_dir.join("4321")).unwrap();
+    fs::create_dir_all(task_dir.join("4322")).unwrap();
+    fs::copy("src/test-data/linux-x64-i7-6850k.auxv", task_dir.join("4321/auxv")).unwrap();
+    fs::write(task_dir.join("4322/auxv"), b"").unwrap();
+
+    dir
+}
+
+#[test]
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+fn fake_procfs_auxv() {
+    let root = fake_procfs("auxv");
+    let procfs = Procfs::with_root(root.path());
+
+    let pairs = procfs.iterate_auxv(Target::Pid(4321)).unwrap()
+        .collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(18, pairs.len());
+    // x86 AT_SYSINFO_EHDR
+    assert_eq!(AuxvPair { key: 33, value: 140724395515904 }, pairs[0]);
+
+    let map = procfs.search_auxv(Target::Current, &[6, 11, 99]).unwrap();
+    assert_eq!(Some(&4096), map.get(&6));
+    assert_eq!(Some(&1000), map.get(&11));
+    assert_eq!(None, map.get(&99));
+
+    assert_eq!([Some(4096), Some(1000), None],
+               procfs.search_auxv_array(Target::Pid(4321), [6, 11, 99]).unwrap());
+
+    let mut reader = procfs.auxv_reader();
+    let snapshot = reader.snapshot_pid(4321, ElfClass::Elf64).unwrap();
+    assert_eq!(&pairs[..], snapshot.pairs());
+    assert_eq!(auxv::procfs::ProcfsAuxvError::IoError, reader.read_pid(1234).unwrap_err());
+}
+
+#[test]
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+fn fake_procfs_maps_and_stat() {
+    let root = fake_procfs("maps");
+    let procfs = Procfs::with_root(root.path());
+
+    let maps = procfs.read_maps(Target::Current).unwrap();
+    assert_eq!(2, maps.len());
+    assert_eq!("/usr/bin/dbus-daemon", maps[0].path);
+    assert_eq!("[stack]", maps[1].path);
+
+    let layout = procfs.read_stack_layout(Target::Pid(4321)).unwrap();
+    assert_eq!(280, layout.start_stack);
+    assert_eq!(480, layout.arg_start);
+    assert_eq!(510, layout.env_end);
+}
+
+#[test]
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+fn fake_procfs_tasks() {
+    let root = fake_procfs("tasks");
+    let procfs = Procfs::with_root(root.path());
+
+    assert_eq!(vec![4321, 4322], procfs.list_tasks(4321).unwrap());
+
+    let tasks = procfs.read_task_auxv(4321, ElfClass::Elf64).unwrap();
+    assert_eq!(4321, tasks[0].tid);
+    assert_eq!(Some(4096), tasks[0].auxv.as_ref().unwrap().get(6));
+    assert_eq!(4322, tasks[1].tid);
+    assert!(tasks[1].auxv.as_ref().unwrap().is_empty());
+
+    let map = procfs.search_auxv(Target::Task { pid: 4321, tid: 4321 }, &[6]).unwrap();
+    assert_eq!(Some(&4096), map.get(&6));
+    assert_eq!(auxv::procfs::ProcfsAuxvError::IoError, procfs.list_tasks(1234).unwrap_err());
+}
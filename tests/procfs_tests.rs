@@ -19,15 +19,124 @@ fn search_procfs_finds_uid_matches_libc() {
     let uid = map.get(&11).unwrap();
 
     let libc_uid = unsafe { libc::getuid() };
-    assert_eq!(libc_uid as u64, *uid as u64);
+    assert_eq!(libc_uid as auxv::AuxvType, *uid);
 }
 
 #[test]
 #[cfg(target_os="linux")]
 fn iterate_procfs_finds_hwcap() {
-    let iter = auxv::procfs::iterate_procfs_auxv().unwrap();
+    let iter = auxv::procfs::iterate_procfs_auxv(std::process::id()).unwrap();
 
     assert_eq!(1, iter.map(|r| r.unwrap())
         .filter(|p| p.key == auxv::AT_HWCAP)
         .count());
 }
+
+#[test]
+#[cfg(target_os = "linux")]
+fn search_procfs_array_matches_map() {
+    // AT_PAGESZ, AT_UID, AT_SECURE, and something that's never set
+    let keys = [auxv::AT_HWCAP, 6, 11, 23, 0xfff];
+    let map = auxv::procfs::search_procfs_auxv(&keys).unwrap();
+
+    let values = auxv::procfs::search_procfs_auxv_array(keys).unwrap();
+    for (key, value) in keys.iter().zip(values.iter()) {
+        assert_eq!(map.get(key).cloned(), *value);
+    }
+    assert_eq!(None, values[4]);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn reader_snapshot_matches_iterator() {
+    let pid = std::process::id();
+    let pairs = auxv::procfs::iterate_procfs_auxv(pid).unwrap()
+        .collect::<Result<Vec<_>, _>>().unwrap();
+
+    let mut reader = auxv::procfs::ProcfsAuxvReader::new();
+    for _ in 0..3 {
+        let snapshot = reader.snapshot_pid(pid, auxv::ElfClass::native()).unwrap();
+        assert_eq!(&pairs[..], snapshot.pairs());
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn reader_missing_pid_is_io_error() {
+    let mut reader = auxv::procfs::ProcfsAuxvReader::new();
+    assert_eq!(auxv::procfs::ProcfsAuxvError::IoError, reader.read_pid(u32::MAX).unwrap_err());
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn reader_pidfd_matches_pid() {
+    use std::os::fd::AsFd;
+
+    let pid = std::process::id();
+    let mut reader = auxv::procfs::ProcfsAuxvReader::new();
+    let expected = reader.read_pid(pid).unwrap().to_vec();
+
+    let pidfd = auxv::procfs::open_pidfd(pid).unwrap();
+    assert_eq!(&expected[..], reader.read_pidfd(pidfd.as_fd()).unwrap());
+    assert_eq!(&expected[..], reader.read_pid_via_pidfd(pid).unwrap());
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn reader_pidfd_of_exited_process() {
+    use std::os::fd::AsFd;
+    use std::process::Command;
+
+    let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+    let pidfd = auxv::procfs::open_pidfd(child.id()).unwrap();
+
+    let mut reader = auxv::procfs::ProcfsAuxvReader::new();
+    // the file is empty until the child has finished starting up
+    let mut found = false;
+    for _ in 0..100 {
+        let snapshot = reader.snapshot_pidfd(pidfd.as_fd(), auxv::ElfClass::native()).unwrap();
+        if snapshot.get(6).is_some() {
+            found = true;
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!(found);
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert_eq!(auxv::procfs::ProcfsAuxvError::ProcessExited,
+               reader.read_pidfd(pidfd.as_fd()).unwrap_err());
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn tasks_share_process_auxv() {
+    use std::sync::mpsc;
+
+    let pid = std::process::id();
+    let (started, wait_started) = mpsc::channel();
+    let (finish, wait_finish) = mpsc::channel::<()>();
+    let thread = std::thread::spawn(move || {
+        started.send(()).unwrap();
+        wait_finish.recv().unwrap();
+    });
+    wait_started.recv().unwrap();
+
+    let tids = auxv::procfs::list_procfs_tasks(pid).unwrap();
+    assert!(tids.len() >= 2);
+    assert!(tids.contains(&pid));
+
+    let procfs = auxv::procfs::Procfs::new();
+    let expected = procfs.auxv_reader().snapshot_pid(pid, auxv::ElfClass::native()).unwrap();
+    for task in procfs.read_task_auxv(pid, auxv::ElfClass::native()).unwrap() {
+        // other test threads can finish in the meantime
+        if let Ok(snapshot) = task.auxv {
+            assert_eq!(expected, snapshot);
+        }
+    }
+
+    finish.send(()).unwrap();
+    thread.join().unwrap();
+}
@@ -0,0 +1,120 @@
+#[cfg(target_os = "linux")]
+extern crate auxv;
+
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+use std::fs;
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+use std::path::Path;
+
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+mod common;
+
+#[cfg(target_os = "linux")]
+use auxv::ElfClass;
+#[cfg(target_os = "linux")]
+use auxv::scan::ProcessScanner;
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+use auxv::procfs::{Procfs, ProcfsAuxvError};
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+use common::TempDir;
+
+/// Add a process to a fake procfs, with `stat` flags, and optionally an auxv file and executable.
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+fn add_process(root: &Path, pid: u32, flags: u64, auxv: Option<&[u8]>, exe: Option<&Path>) {
+    let dir = root.join(pid.to_string());
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("stat"), format!("{} (fake) S 1 1 1 0 -1 {} 0 0 0 0", pid, flags)).unwrap();
+    if let Some(auxv) = auxv {
+        fs::write(dir.join("auxv"), auxv).unwrap();
+    }
+    if let Some(exe) = exe {
+        std::os::unix::fs::symlink(exe, dir.join("exe")).unwrap();
+    }
+}
+
+/// A fake procfs with:
+///
+/// - 10: a 64-bit process
+/// - 11: a kernel thread
+/// - 12: a 32-bit process
+/// - 13: a 64-bit setuid process
+/// - 14: a process whose auxv can't be read
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+fn fake_procfs() -> TempDir {
+    let dir = TempDir::new("fake-scan");
+    let root = dir.path();
+
+    let exe64 = root.join("exe64");
+    fs::write(&exe64, b"\x7fELF\x02\x01\x01\x00").unwrap();
+    let exe32 = root.join("exe32");
+    fs::write(&exe32, b"\x7fELF\x01\x01\x01\x00").unwrap();
+
+    let auxv64 = fs::read("src/test-data/linux-x64-i7-6850k.auxv").unwrap();
+    let auxv32 = fs::read("src/test-data/macos-virtualbox-linux-x86-4850HQ.auxv").unwrap();
+    // set AT_SECURE, the 15th pair
+    let mut secure = auxv64.clone();
+    assert_eq!(23, secure[14 * 16]);
+    secure[14 * 16 + 8] = 1;
+
+    add_process(root, 10, 0x0040_0000, Some(&auxv64), Some(&exe64));
+    add_process(root, 11, 0x0020_8040, None, None);
+    add_process(root, 12, 0x0040_0000, Some(&auxv32), Some(&exe32));
+    add_process(root, 13, 0x0040_0100, Some(&secure), Some(&exe64));
+    add_process(root, 14, 0x0040_0000, None, Some(&exe64));
+    // not processes
+    fs::create_dir_all(root.join("sys")).unwrap();
+    std::os::unix::fs::symlink("10", root.join("self")).unwrap();
+
+    dir
+}
+
+#[test]
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+fn scan_fake_procfs() {
+    let root = fake_procfs();
+    let scanner = ProcessScanner::with_procfs(Procfs::with_root(root.path())).threads(3);
+
+    let processes = scanner.scan().unwrap();
+    assert_eq!(vec![10, 11, 12, 13, 14], processes.iter().map(|p| p.pid).collect::<Vec<_>>());
+
+    let p = &processes[0];
+    assert_eq!(Some(root.path().join("exe64")), p.exe);
+    assert_eq!(Some(ElfClass::Elf64), p.class);
+    assert!(!p.kernel_thread);
+    assert!(!p.is_32_bit());
+    assert_eq!(Some(false), p.is_secure());
+    assert_eq!(Some(4096), p.value(6));
+
+    let p = &processes[1];
+    assert!(p.kernel_thread);
+    assert_eq!(None, p.exe);
+    assert!(p.auxv.as_ref().unwrap().is_empty());
+
+    let p = &processes[2];
+    assert!(p.is_32_bit());
+    assert_eq!(Some(false), p.is_secure());
+    // 32-bit entries, read correctly on a 64-bit host
+    assert_eq!(Some(134512692), p.value(auxv::AT_PHDR));
+
+    assert_eq!(Some(true), processes[3].is_secure());
+
+    let p = &processes[4];
+    assert_eq!(Err(ProcfsAuxvError::IoError), p.auxv);
+    assert_eq!(None, p.is_secure());
+
+    assert_eq!(processes[2], scanner.scan_pid(12));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn scan_finds_current_process() {
+    let pid = std::process::id();
+    let processes = ProcessScanner::new().scan().unwrap();
+
+    let me = processes.iter().find(|p| p.pid == pid).unwrap();
+    assert_eq!(Some(std::env::current_exe().unwrap()), me.exe);
+    assert_eq!(Some(ElfClass::native()), me.class);
+    assert!(!me.kernel_thread);
+    assert_eq!(Some(false), me.is_secure());
+    assert_eq!(auxv::procfs::search_procfs_auxv(&[6]).unwrap().get(&6).cloned(), me.value(6));
+}
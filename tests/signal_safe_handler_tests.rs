@@ -0,0 +1,60 @@
+// The handler test has a binary to itself: a test running alongside it could fill the signal-safe
+// storage before the signal arrives, and then the handler wouldn't have to fill it.
+
+#[cfg(target_os="linux")]
+extern crate auxv;
+#[cfg(target_os="linux")]
+extern crate libc;
+
+#[cfg(target_os="linux")]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+#[cfg(target_os="linux")]
+use auxv::procfs::search_procfs_auxv;
+#[cfg(target_os="linux")]
+use auxv::signal_safe::signal_safe_auxv_value;
+#[cfg(target_os="linux")]
+use auxv::{AT_HWCAP, AT_PHDR, AT_SYSINFO_EHDR};
+
+#[cfg(target_os="linux")]
+static HANDLER_RAN: AtomicBool = AtomicBool::new(false);
+#[cfg(target_os="linux")]
+static SYSINFO_EHDR: AtomicUsize = AtomicUsize::new(0);
+#[cfg(target_os="linux")]
+static PHDR: AtomicUsize = AtomicUsize::new(0);
+#[cfg(target_os="linux")]
+static HWCAP: AtomicUsize = AtomicUsize::new(0);
+#[cfg(target_os="linux")]
+static ERRNO_KEPT: AtomicBool = AtomicBool::new(false);
+
+#[cfg(target_os="linux")]
+extern "C" fn handler(_: libc::c_int) {
+    let errno = unsafe { *libc::__errno_location() };
+
+    let value = |key| signal_safe_auxv_value(key).map(|v| v as usize).unwrap_or(0);
+    SYSINFO_EHDR.store(value(AT_SYSINFO_EHDR), Ordering::SeqCst);
+    PHDR.store(value(AT_PHDR), Ordering::SeqCst);
+    HWCAP.store(value(AT_HWCAP), Ordering::SeqCst);
+
+    ERRNO_KEPT.store(errno == unsafe { *libc::__errno_location() }, Ordering::SeqCst);
+    HANDLER_RAN.store(true, Ordering::SeqCst);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_signal_safe_lookup_in_handler() {
+    unsafe {
+        libc::signal(libc::SIGUSR1, handler as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        *libc::__errno_location() = libc::EINVAL;
+        assert_eq!(0, libc::raise(libc::SIGUSR1));
+    }
+
+    assert!(HANDLER_RAN.load(Ordering::SeqCst));
+    assert!(ERRNO_KEPT.load(Ordering::SeqCst));
+
+    // not getauxval: glibc on x86 replaces AT_HWCAP with its own bits
+    let procfs = search_procfs_auxv(&[AT_SYSINFO_EHDR, AT_PHDR, AT_HWCAP]).unwrap();
+    assert_eq!(procfs[&AT_SYSINFO_EHDR] as usize, SYSINFO_EHDR.load(Ordering::SeqCst));
+    assert_eq!(procfs[&AT_PHDR] as usize, PHDR.load(Ordering::SeqCst));
+    assert_eq!(procfs[&AT_HWCAP] as usize, HWCAP.load(Ordering::SeqCst));
+}
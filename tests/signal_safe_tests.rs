@@ -0,0 +1,23 @@
+#[cfg(target_os="linux")]
+extern crate auxv;
+
+#[cfg(target_os="linux")]
+use auxv::procfs::search_procfs_auxv;
+#[cfg(target_os="linux")]
+use auxv::signal_safe::{prepare_signal_safe_auxv, signal_safe_auxv_value, SignalSafeAuxvError};
+#[cfg(target_os="linux")]
+use auxv::{AT_HWCAP, AT_PHDR};
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_prepared_lookup_matches_procfs() {
+    prepare_signal_safe_auxv().unwrap();
+
+    // AT_PAGESZ, AT_UID, AT_SECURE
+    let keys = [6, 11, 23, AT_PHDR, AT_HWCAP];
+    let procfs = search_procfs_auxv(&keys).unwrap();
+    for key in &keys {
+        assert_eq!(procfs[key], signal_safe_auxv_value(*key).unwrap());
+    }
+    assert_eq!(SignalSafeAuxvError::NotFound, signal_safe_auxv_value(0xfff).unwrap_err());
+}
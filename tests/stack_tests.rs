@@ -13,7 +13,7 @@ use auxv::procfs::iterate_procfs_auxv;
 #[test]
 #[cfg(target_os="linux")]
 fn auxv_via_stack_equals_auxv_via_procfs() {
-    let procfs: Vec<AuxvPair> = iterate_procfs_auxv().unwrap()
+    let procfs: Vec<AuxvPair> = iterate_procfs_auxv(std::process::id()).unwrap()
         .map(|r| r.unwrap())
         .collect();
     unsafe {
@@ -45,7 +45,51 @@ fn test_stack_auxv_uid_matches_libc_uid() {
             .unwrap();
 
         let libc_uid = libc::getuid();
-        assert_eq!(libc_uid as u64, auxv_uid as u64);
+        assert_eq!(libc_uid as auxv::AuxvType, auxv_uid);
     }
 }
 
+
+#[test]
+#[cfg(target_os="linux")]
+fn auxv_via_checked_stack_equals_auxv_via_procfs() {
+    let procfs: Vec<AuxvPair> = iterate_procfs_auxv(std::process::id()).unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    let stack: Vec<AuxvPair> = iterate_stack_auxv_checked().unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(procfs, stack);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn environ_is_original_before_modification() {
+    assert!(environ_is_original().unwrap());
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn auxv_via_verified_stack_equals_auxv_via_procfs() {
+    let procfs: Vec<AuxvPair> = iterate_procfs_auxv(std::process::id()).unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    let stack: Vec<AuxvPair> = iterate_stack_auxv_verified().unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(procfs, stack);
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn initial_stack_args_match_std_args() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let initial = initial_stack().unwrap();
+    let args: Vec<std::ffi::OsString> = std::env::args_os().collect();
+
+    assert_eq!(args.len(), initial.argc());
+    // the test harness leaves argv alone
+    assert_eq!(args.iter().map(|a| a.as_bytes()).collect::<Vec<_>>(),
+               unsafe { initial.args() }.map(|a| a.to_bytes()).collect::<Vec<_>>());
+}
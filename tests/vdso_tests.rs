@@ -0,0 +1,102 @@
+#[cfg(target_os = "linux")]
+extern crate auxv;
+#[cfg(target_os = "linux")]
+extern crate libc;
+
+#[cfg(target_os = "linux")]
+use std::io::Read;
+
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+mod common;
+
+#[cfg(target_os = "linux")]
+use auxv::vdso::current_vdso;
+
+#[test]
+#[cfg(target_os = "linux")]
+fn current_vdso_has_symbols() {
+    let vdso = current_vdso().unwrap();
+
+    assert!(vdso.symbols().count() > 0);
+}
+
+#[test]
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn current_vdso_clock_gettime_matches_libc() {
+    let vdso = current_vdso().unwrap();
+    let sym = vdso.lookup("__vdso_clock_gettime", "LINUX_2.6").unwrap();
+
+    unsafe {
+        let vdso_clock_gettime: extern "C" fn(libc::clockid_t, *mut libc::timespec) -> libc::c_int =
+            std::mem::transmute(sym.address);
+
+        let mut libc_ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        let mut vdso_ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        assert_eq!(0, libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut libc_ts));
+        assert_eq!(0, vdso_clock_gettime(libc::CLOCK_MONOTONIC, &mut vdso_ts));
+
+        // monotonic, and surely less than a minute apart
+        assert!(vdso_ts.tv_sec >= libc_ts.tv_sec);
+        assert!(vdso_ts.tv_sec - libc_ts.tv_sec < 60);
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn current_vdso_kernel_version_matches_uname() {
+    let version = current_vdso().unwrap().kernel_version().unwrap();
+
+    let release = unsafe {
+        let mut uts: libc::utsname = std::mem::zeroed();
+        assert_eq!(0, libc::uname(&mut uts));
+        std::ffi::CStr::from_ptr(uts.release.as_ptr()).to_str().unwrap().to_owned()
+    };
+    // e.g. "6.1.0-18-amd64"
+    let mut parts = release.split(|c: char| !c.is_ascii_digit());
+    let major: u32 = parts.next().unwrap().parse().unwrap();
+    let minor: u32 = parts.next().unwrap().parse().unwrap();
+    assert_eq!((major, minor), (version.major, version.minor));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn dump_process_vdso_matches_current_vdso() {
+    let sysinfo_ehdr = *auxv::procfs::search_procfs_auxv(&[auxv::AT_SYSINFO_EHDR]).unwrap()
+        .get(&auxv::AT_SYSINFO_EHDR)
+        .unwrap();
+    let path = std::env::temp_dir().join(format!("auxv-vdso-test-{}.so", std::process::id()));
+
+    auxv::vdso::dump_process_vdso(std::process::id(), sysinfo_ehdr, &path).unwrap();
+    let mut dumped = Vec::new();
+    std::fs::File::open(&path).unwrap().read_to_end(&mut dumped).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let current = current_vdso().unwrap();
+    assert_eq!(current.image().bytes(), &dumped[..]);
+    let parsed = auxv::vdso::Vdso::parse(&dumped, sysinfo_ehdr as usize).unwrap();
+    assert_eq!(current.symbols().collect::<Vec<_>>(), parsed.symbols().collect::<Vec<_>>());
+}
+
+#[test]
+#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+fn read_procfs_vdso_from_fake_procfs() {
+    use auxv::procfs::{Procfs, Target};
+
+    let dir = common::TempDir::new("fake-proc-vdso");
+    let image = std::fs::read("src/test-data/linux-x64-vdso.so").unwrap();
+    let pid_dir = dir.path().join("4321");
+    std::fs::create_dir_all(&pid_dir).unwrap();
+    std::fs::write(pid_dir.join("maps"),
+                   "00003000-00005000 r-xp 00000000 00:00 0          [vdso]\n").unwrap();
+    // the vDSO at 0x3000 in the process's memory
+    let mut mem = vec![0; 0x3000];
+    mem.extend_from_slice(&image);
+    std::fs::write(pid_dir.join("mem"), mem).unwrap();
+
+    let read = auxv::vdso::read_procfs_vdso(&Procfs::with_root(dir.path()), Target::Pid(4321),
+                                            0x3000).unwrap();
+    // trimmed to what the headers describe
+    let extent = auxv::elf::ElfImage::parse(&image).unwrap().extent();
+    assert_eq!(extent, read.len());
+    assert!(image[..extent] == read[..]);
+}